@@ -10,55 +10,264 @@ async function setup_demo1() {
 
     let fixed_update_interval = 1000 / 60;
 
+    let read_string = (pointer: number, length: number) => {
+        let bytes = new Uint8Array((exports.memory as WebAssembly.Memory).buffer, pointer, length);
+        return TEXT_DECODER.decode(bytes);
+    };
+
+    // Images that have finished loading, by image handle.
+    let images = new Map<number, HTMLImageElement>();
+    let requested_images = new Set<number>();
+
+    // The latest tick and hash halves passed to `external_state_hash`, waiting to be sent to other peers.
+    let state_hash_report: [number, number, number] | null = null;
+    let last_reported_tick = -1;
+    // Players already logged as desynced. Kept out here because the Wasm side forgets when it rolls back
+    // and replays a report, which would log the same desync again.
+    let desynced_players = new Set<number>();
+
+    // The latest tick passed to `external_fixed_update` and when this peer finished it.
+    // Rollbacks run earlier ticks again, so only a newer tick moves the time forward.
+    let last_fixed_update_tick = -1;
+    let last_fixed_update_time = 0;
+
     let imports = {
         env: {
-            set_color: function (r: number, g: number, b: number, a: number) {
-                context.fillStyle = `rgba(${r}, ${g}, ${b}, ${a})`;
-            },
-            draw_circle: function (x: number, y: number, radius: number) {
-                //extra_elements.push(addSVGCircle(x, y, radius));
-                context.beginPath();
-                context.arc(x, y, radius, 0, 2 * Math.PI);
-                context.fill();
-            },
-            begin_path: function () {
-                context.beginPath();
-            },
-            move_to: function (x: number, y: number) {
-                context.moveTo(x, y);
-            },
-            line_to: function (x: number, y: number) {
-                context.lineTo(x, y);
+            load_image: function (image: number, path_pointer: number, path_length: number) {
+                // Rollbacks can replay a call and every peer asks again when a player joins, so only load each image once.
+                if (requested_images.has(image)) {
+                    return;
+                }
+                requested_images.add(image);
+
+                let element = new Image();
+                element.onload = () => {
+                    images.set(image, element);
+                    // Every player reports the load. The Rust side ignores all but the first report.
+                    if (exports.image_loaded) {
+                        exports.image_loaded(image, element.naturalWidth, element.naturalHeight);
+                    }
+                };
+                element.onerror = () => {
+                    console.error("Could not load image: ", element.src);
+                };
+                element.src = read_string(path_pointer, path_length);
             },
-            stroke: function () {
-                context.stroke();
+            measure_text: function (family_pointer: number, family_length: number, size: number, text_pointer: number, text_length: number) {
+                context.save();
+                context.font = css_font(read_string(family_pointer, family_length), size);
+                let width = context.measureText(read_string(text_pointer, text_length)).width;
+                context.restore();
+                return width;
             },
-            fill: function () {
-                context.fill();
+            external_state_hash: function (tick: number, hash_high: number, hash_low: number) {
+                state_hash_report = [tick, hash_high, hash_low];
             },
-            translate: function (x: number, y: number) {
-                context.translate(x, y);
+            external_state_hash_mismatch: function (player: number, tick: number) {
+                if (!desynced_players.has(player)) {
+                    desynced_players.add(player);
+                    console.error(`Desync: player ${player} has a different state at tick ${tick}`);
+                }
             },
-            rotate: function (radians: number) {
-                context.rotate(radians);
+            external_fixed_update: function (tick: number) {
+                if (tick > last_fixed_update_tick) {
+                    last_fixed_update_tick = tick;
+                    last_fixed_update_time = performance.now();
+                }
             },
-            draw_rect: function (x: number, y: number, width: number, height: number) {
-                context.beginPath();
-                context.rect(x, y, width, height);
-                context.fill();
+            // Decodes a draw list. The format is documented in `rust_project/src/mini_engine/draw_list.rs`.
+            submit: function (pointer: number, length: number) {
+                let view = new DataView((exports.memory as WebAssembly.Memory).buffer, pointer, length);
+                let offset = 0;
+                let u8 = () => {
+                    let value = view.getUint8(offset);
+                    offset += 1;
+                    return value;
+                };
+                let u32 = () => {
+                    let value = view.getUint32(offset, true);
+                    offset += 4;
+                    return value;
+                };
+                let f32 = () => {
+                    let value = view.getFloat32(offset, true);
+                    offset += 4;
+                    return value;
+                };
+                let str = () => {
+                    let length = u32();
+                    let value = read_string(pointer + offset, length);
+                    offset += length;
+                    return value;
+                };
+
+                // The Rust side tracks the transform and text state itself, assuming they are reset for every draw list.
+                context.setTransform(1, 0, 0, 1, 0, 0);
+                context.font = css_font(DEFAULT_FONT_FAMILY, DEFAULT_FONT_SIZE);
+                context.textAlign = "left";
+                context.textBaseline = "alphabetic";
+
+                while (offset < length) {
+                    let opcode = u8();
+                    switch (opcode) {
+                        case 0: {
+                            let r = u8(), g = u8(), b = u8(), a = u8();
+                            context.fillStyle = `rgba(${r}, ${g}, ${b}, ${a / 255})`;
+                            context.strokeStyle = context.fillStyle;
+                            break;
+                        }
+                        case 1: {
+                            let x = f32(), y = f32(), radius = f32();
+                            context.beginPath();
+                            context.arc(x, y, radius, 0, 2 * Math.PI);
+                            context.fill();
+                            break;
+                        }
+                        case 2: {
+                            let x = f32(), y = f32(), width = f32(), height = f32();
+                            context.beginPath();
+                            context.rect(x, y, width, height);
+                            context.fill();
+                            break;
+                        }
+                        case 3: {
+                            let a = f32(), b = f32(), c = f32(), d = f32(), e = f32(), f = f32();
+                            context.setTransform(a, b, c, d, e, f);
+                            break;
+                        }
+                        case 4:
+                            context.beginPath();
+                            break;
+                        case 5: {
+                            let x = f32(), y = f32();
+                            context.moveTo(x, y);
+                            break;
+                        }
+                        case 6: {
+                            let x = f32(), y = f32();
+                            context.lineTo(x, y);
+                            break;
+                        }
+                        case 7:
+                            context.fill();
+                            break;
+                        case 8:
+                            context.stroke();
+                            break;
+                        case 9:
+                            context.lineWidth = f32();
+                            break;
+                        case 10:
+                            context.lineCap = LINE_CAPS[u8()];
+                            break;
+                        case 11:
+                            context.lineJoin = LINE_JOINS[u8()];
+                            break;
+                        case 12: {
+                            let dash_offset = f32();
+                            let count = u32();
+                            let segments: number[] = [];
+                            for (let i = 0; i < count; i++) {
+                                segments.push(f32());
+                            }
+                            context.setLineDash(segments);
+                            context.lineDashOffset = dash_offset;
+                            break;
+                        }
+                        case 13: {
+                            let control_x = f32(), control_y = f32(), x = f32(), y = f32();
+                            context.quadraticCurveTo(control_x, control_y, x, y);
+                            break;
+                        }
+                        case 14: {
+                            let control_0_x = f32(), control_0_y = f32();
+                            let control_1_x = f32(), control_1_y = f32();
+                            let x = f32(), y = f32();
+                            context.bezierCurveTo(control_0_x, control_0_y, control_1_x, control_1_y, x, y);
+                            break;
+                        }
+                        case 15: {
+                            let x = f32(), y = f32(), radius = f32();
+                            let start_angle = f32(), end_angle = f32();
+                            let counterclockwise = u8() != 0;
+                            context.arc(x, y, radius, start_angle, end_angle, counterclockwise);
+                            break;
+                        }
+                        case 16: {
+                            let x1 = f32(), y1 = f32(), x2 = f32(), y2 = f32(), radius = f32();
+                            context.arcTo(x1, y1, x2, y2, radius);
+                            break;
+                        }
+                        case 17:
+                            context.closePath();
+                            break;
+                        case 18: {
+                            let x = f32(), y = f32(), radius_x = f32(), radius_y = f32(), rotation = f32();
+                            context.beginPath();
+                            context.ellipse(x, y, radius_x, radius_y, rotation, 0, 2 * Math.PI);
+                            context.fill();
+                            break;
+                        }
+                        case 19: {
+                            let size = f32();
+                            let family = str();
+                            context.font = css_font(family, size);
+                            break;
+                        }
+                        case 20:
+                            context.textAlign = TEXT_ALIGNS[u8()];
+                            break;
+                        case 21:
+                            context.textBaseline = TEXT_BASELINES[u8()];
+                            break;
+                        case 22: {
+                            let x = f32(), y = f32();
+                            let text = str();
+                            context.fillText(text, x, y);
+                            break;
+                        }
+                        case 23: {
+                            let image = u32();
+                            let source_x = f32(), source_y = f32(), source_width = f32(), source_height = f32();
+                            let x = f32(), y = f32(), width = f32(), height = f32();
+                            let element = images.get(image);
+                            if (element) {
+                                context.drawImage(element, source_x, source_y, source_width, source_height, x, y, width, height);
+                            }
+                            break;
+                        }
+                        default:
+                            console.error("Unknown draw command: ", opcode);
+                            return;
+                    }
+                }
             },
-            set_transform: function (a: number, b: number, c: number, d: number, e: number, f: number) {
-                context.setTransform(a, b, c, d, e, f);
-            }
         },
     };
 
+    // Text drawn before a font has loaded falls back to another font, so load the bundled fonts up front.
+    await Promise.all([DEFAULT_FONT_FAMILY, "InterDisplay", "PermanentMarker"].map(family => document.fonts.load(css_font(family, DEFAULT_FONT_SIZE))));
+
+    // Each peer picks its own seed, but only the first one sent in a session is used.
+    // It's sent like any other call, so every peer agrees on it, and a new session in the same room gets a new one.
+    let session_seed = () => crypto.getRandomValues(new Uint32Array(1))[0];
+
+    let connected = false;
+
     let wasm_binary = await fetch("rust_project.wasm").then(response => response.arrayBuffer());
 
     let result = await Tangle.instantiate(new Uint8Array(wasm_binary), imports, {
         fixed_update_interval,
         on_state_change_callback: (state) => {
             if (state == TangleState.Connected) {
+                connected = true;
+                // The game is built when the seed arrives and ignores everything sent before it, so it goes first.
+                if (exports.set_session_seed) {
+                    exports.set_session_seed(session_seed());
+                }
+                if (exports.set_fixed_update_interval) {
+                    exports.set_fixed_update_interval(fixed_update_interval);
+                }
                 canvas.style.opacity = "1.0";
                 if (exports.player_joined) {
                     exports.player_joined(UserId);
@@ -69,29 +278,71 @@ async function setup_demo1() {
     let tangle = result.tangle;
     let exports = result.instance.exports;
 
-    document.onpointerdown = async (event) => {
+    // The arguments every pointer export takes, in the order `mini_engine`'s `register_game!` expects.
+    let pointer_arguments = (event: PointerEvent) => {
         let rect = canvas.getBoundingClientRect();
+        return [
+            UserId,
+            event.pointerId,
+            event.clientX - rect.left,
+            event.clientY - rect.top,
+            POINTER_KINDS.indexOf(event.pointerType),
+            event.button,
+            event.pressure,
+            event.tiltX,
+            event.tiltY,
+            event.width,
+            event.height,
+        ];
+    };
+
+    document.onpointerdown = async (event) => {
         if (exports.pointer_down) {
-            exports.pointer_down(UserId, event.pointerId, event.clientX - rect.left, event.clientY - rect.top);
+            exports.pointer_down(...pointer_arguments(event));
         }
     };
 
     document.onpointermove = async (event) => {
-        let rect = canvas.getBoundingClientRect();
         if (exports.pointer_move) {
-            exports.pointer_move(UserId, event.pointerId, event.clientX - rect.left, event.clientY - rect.top);
+            exports.pointer_move(...pointer_arguments(event));
         }
     };
 
     document.onpointerup = async (event) => {
-        let rect = canvas.getBoundingClientRect();
-
         if (exports.pointer_up) {
-            exports.pointer_up(UserId, event.pointerId, event.pointerType === "mouse", event.clientX - rect.left, event.clientY - rect.top);
+            exports.pointer_up(...pointer_arguments(event));
+        }
+    };
+
+    document.onpointercancel = async (event) => {
+        if (exports.pointer_cancel) {
+            exports.pointer_cancel(...pointer_arguments(event));
+        }
+    };
+
+    // The secondary button is used by the demo, so keep the context menu from opening over it.
+    canvas.oncontextmenu = (event) => {
+        event.preventDefault();
+    };
+
+    canvas.onwheel = async (event) => {
+        // Keep the page from scrolling while the wheel is used in the demo.
+        event.preventDefault();
+        let rect = canvas.getBoundingClientRect();
+        if (exports.wheel) {
+            exports.wheel(UserId, event.deltaX, event.deltaY, event.deltaMode, event.clientX - rect.left, event.clientY - rect.top);
         }
     };
 
+    // Keys currently held by this user, so they can be released if the window loses focus.
+    let held_keys = new Set<number>();
+
     document.onkeydown = async (event) => {
+        // Held keys repeat, but the Rust side only cares about the initial press.
+        if (event.repeat) {
+            return;
+        }
+        held_keys.add(event.keyCode);
         if (exports.key_down) {
             exports.key_down(UserId, event.keyCode);
         }
@@ -104,26 +355,140 @@ async function setup_demo1() {
     };
 
     document.onkeyup = async (event) => {
+        held_keys.delete(event.keyCode);
         if (exports.key_up) {
             exports.key_up(UserId, event.keyCode);
         }
     };
 
+    // Key up events are not received while the window is unfocused.
+    window.onblur = async () => {
+        for (let key_code of held_keys) {
+            if (exports.key_up) {
+                exports.key_up(UserId, key_code);
+            }
+        }
+        held_keys.clear();
+    };
+
+    // Browsers only report gamepad buttons and axes by polling, so changes are found by comparing
+    // against the last state sent to the Rust side, by gamepad index.
+    let reported_gamepads = new Map<number, { buttons: { pressed: boolean, value: number }[], axes: number[] }>();
+
+    let poll_gamepads = () => {
+        let gamepads = navigator.getGamepads();
+        for (let index of reported_gamepads.keys()) {
+            if (!gamepads[index]) {
+                reported_gamepads.delete(index);
+                if (exports.gamepad_disconnected) {
+                    exports.gamepad_disconnected(UserId, index);
+                }
+            }
+        }
+
+        for (let gamepad of gamepads) {
+            if (!gamepad) {
+                continue;
+            }
+            let reported = reported_gamepads.get(gamepad.index);
+            if (!reported) {
+                reported = { buttons: [], axes: [] };
+                reported_gamepads.set(gamepad.index, reported);
+                if (exports.gamepad_connected) {
+                    exports.gamepad_connected(UserId, gamepad.index);
+                }
+            }
+
+            gamepad.buttons.forEach((button, i) => {
+                let last = reported!.buttons[i] ?? { pressed: false, value: 0 };
+                if (button.pressed != last.pressed || Math.abs(button.value - last.value) > GAMEPAD_EPSILON) {
+                    reported!.buttons[i] = { pressed: button.pressed, value: button.value };
+                    if (exports.gamepad_button) {
+                        exports.gamepad_button(UserId, gamepad.index, i, button.pressed ? 1 : 0, button.value);
+                    }
+                }
+            });
+
+            gamepad.axes.forEach((value, i) => {
+                // Every change is sent to every peer, so ignore the jitter of a resting stick.
+                if (Math.abs(value - (reported!.axes[i] ?? 0)) > GAMEPAD_EPSILON) {
+                    reported!.axes[i] = value;
+                    if (exports.gamepad_axis) {
+                        exports.gamepad_axis(UserId, gamepad.index, i, value);
+                    }
+                }
+            });
+        }
+    };
+
+    // The last viewport sent to the Rust side. Every player's viewport is shared so pointer positions
+    // can be converted to world positions the same way everywhere, so only send it when it changes.
+    let reported_viewport = { width: 0, height: 0, device_pixel_ratio: 0 };
+
     async function animation() {
-        if (canvas.width != canvas.clientWidth || canvas.height != canvas.clientHeight) {
-            canvas.width = canvas.clientWidth;
-            canvas.height = canvas.clientHeight;
+        let device_pixel_ratio = window.devicePixelRatio;
+        let width = Math.round(canvas.clientWidth * device_pixel_ratio);
+        let height = Math.round(canvas.clientHeight * device_pixel_ratio);
+        if (canvas.width != width || canvas.height != height) {
+            canvas.width = width;
+            canvas.height = height;
+        }
+
+        if (connected && exports.resize && (
+            reported_viewport.width != canvas.clientWidth ||
+            reported_viewport.height != canvas.clientHeight ||
+            reported_viewport.device_pixel_ratio != device_pixel_ratio)) {
+            reported_viewport = { width: canvas.clientWidth, height: canvas.clientHeight, device_pixel_ratio };
+            exports.resize(UserId, canvas.clientWidth, canvas.clientHeight, device_pixel_ratio);
+        }
+
+        if (connected) {
+            poll_gamepads();
         }
 
         context.clearRect(0, 0, context.canvas.width, context.canvas.height);
 
-        exports.draw.callAndRevert();
+        // How far this frame is from the last fixed update to the next one, so the Rust side can blend the last two updates.
+        let alpha = Math.min(Math.max((performance.now() - last_fixed_update_time) / fixed_update_interval, 0), 1);
+        exports.draw.callAndRevert(UserId, alpha);
+
+        if (connected && DEBUG_DESYNC) {
+            if (state_hash_report && state_hash_report[0] != last_reported_tick) {
+                last_reported_tick = state_hash_report[0];
+                exports.check_state_hash(UserId, ...state_hash_report);
+            }
+            exports.state_hash.callAndRevert(DESYNC_CHECK_DELAY);
+        }
 
         window.requestAnimationFrame(animation);
     }
     animation();
 }
 
+// Matches `PointerKind::from_u32`. Unknown pointer types become -1, which is treated as a mouse.
+const POINTER_KINDS = ["mouse", "touch", "pen"];
+const LINE_CAPS: CanvasLineCap[] = ["butt", "round", "square"];
+const LINE_JOINS: CanvasLineJoin[] = ["miter", "round", "bevel"];
+const TEXT_ALIGNS: CanvasTextAlign[] = ["left", "center", "right"];
+const TEXT_BASELINES: CanvasTextBaseline[] = ["alphabetic", "top", "middle", "bottom"];
+// How much a gamepad button value or axis has to change before the change is sent.
+const GAMEPAD_EPSILON = 0.01;
+// Set `localStorage.debug_desync` on every peer to compare state hashes and log the tick where they first differ.
+// It's not part of the address because Tangle picks the room from the address.
+const DEBUG_DESYNC = localStorage.getItem("debug_desync") != null;
+// How many fixed updates old the compared hashes are, so late events have usually arrived and been rolled back in.
+const DESYNC_CHECK_DELAY = 30;
+
+// These match `DEFAULT_FONT_FAMILY` and `DEFAULT_FONT_SIZE` in `rust_project/src/mini_engine/text.rs`.
+const DEFAULT_FONT_FAMILY = "Inter";
+const DEFAULT_FONT_SIZE = 16;
+
+const TEXT_DECODER = new TextDecoder();
+
+function css_font(family: string, size: number) {
+    return `${size}px ${family}`;
+}
+
 function set_random_name() {
     if (!window.location.hash) {
         window.location.hash += ADJECTIVES[Math.floor(Math.random() * ADJECTIVES.length)];
@@ -2,7 +2,7 @@ set -e
 
 npm run build
 cd rust_project
-cargo build --release
+cargo build --release --target wasm32-unknown-unknown
 cd ..
 cp rust_project/target/wasm32-unknown-unknown/release/rust_project.wasm dist/rust_project.wasm
 devserver --path dist --address 0.0.0.0:8080
\ No newline at end of file
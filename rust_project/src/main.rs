@@ -1,19 +1,67 @@
+// `mini_engine` is written to be reused by other games, so this one leaves parts of its API unused.
+#[allow(dead_code, unused_imports)]
 mod mini_engine;
-use std::collections::HashMap;
+#[cfg(test)]
+mod tests;
+use std::hash::Hash;
 
 use kmath::*;
 use mini_engine::*;
 use rapier2d::{na::Point2, prelude::*};
 
-const WORLD_SCALE_FACTOR: f32 = 0.05 / 20.0;
+/// The area of the world, in world units, that is always in view.
+const VIEW_WIDTH: f32 = 2.5;
+const VIEW_HEIGHT: f32 = 1.75;
+/// Used until a player's host reports the size of their canvas.
+const DEFAULT_VIEWPORT: Viewport = Viewport::new(1000.0, 700.0, 1.0);
+const SELECTION_OUTLINE_WIDTH: f32 = 0.01;
+/// Radians a held body turns per pixel scrolled.
+const WHEEL_ROTATION_SPEED: f32 = 0.005;
+/// How much the view zooms per pixel scrolled. Scrolling up zooms in.
+const WHEEL_ZOOM_SPEED: f32 = 0.002;
+/// Radians a held body turns per fixed update while a rotate key is held or the stick is pushed all the way.
+const KEY_ROTATION_SPEED: f32 = 0.05;
+/// How much of the pointer's speed a released body keeps.
+const THROW_SPEED_SCALE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Action {
+    /// Picks up the body under a pointer. Only pointer button bindings apply.
+    Grab,
+    /// Turns held bodies. Positive values turn clockwise.
+    Rotate,
+    /// Adds a random body at the player's pointer.
+    Spawn,
+    /// Removes the body under the player's pointer.
+    Delete,
+}
+
+fn default_actions() -> ActionMap<Action> {
+    let mut actions = ActionMap::new();
+    actions.bind(Action::Grab, Binding::PointerButton(PointerButton::Primary));
+    actions.bind_scaled(Action::Rotate, Binding::Wheel, WHEEL_ROTATION_SPEED);
+    actions.bind_scaled(Action::Rotate, Binding::Key(Key::Q), -KEY_ROTATION_SPEED);
+    actions.bind_scaled(Action::Rotate, Binding::Key(Key::E), KEY_ROTATION_SPEED);
+    actions.bind_scaled(
+        Action::Rotate,
+        Binding::GamepadAxis(GamepadAxis::RightX),
+        KEY_ROTATION_SPEED,
+    );
+    actions.bind(Action::Spawn, Binding::Key(Key::Space));
+    actions.bind(Action::Spawn, Binding::GamepadButton(GamepadButton::South));
+    actions.bind(
+        Action::Delete,
+        Binding::PointerButton(PointerButton::Secondary),
+    );
+    actions.bind(Action::Delete, Binding::Key(Key::Delete));
+    actions.bind(Action::Delete, Binding::Key(Key::Backspace));
+    actions
+}
 
+/// What the game adds to `mini_engine`'s `PointerState` for each pointer.
 struct PlayerPointerInfo {
     moving_collider: Option<ColliderHandle>,
-    cursor_position: Vec2,
-    last_cursor_position: Vec2,
     offset: Vec2,
-    cursor_down: bool,
-    render: bool,
     color: (u8, u8, u8),
 }
 
@@ -21,11 +69,7 @@ impl PlayerPointerInfo {
     fn new(color: (u8, u8, u8)) -> Self {
         Self {
             moving_collider: None,
-            cursor_position: Vec2::ZERO,
-            last_cursor_position: Vec2::ZERO,
             offset: Vec2::ZERO,
-            cursor_down: false,
-            render: false,
             color,
         }
     }
@@ -34,6 +78,8 @@ impl PlayerPointerInfo {
 struct PhysicsObject {
     rigid_body_handle: RigidBodyHandle,
     color: (u8, u8, u8),
+    /// The body's position before the latest fixed update, so drawing can blend towards the current one.
+    previous_position: Isometry<Real>,
 }
 
 const COLORS: &[(u8, u8, u8)] = &[
@@ -44,283 +90,547 @@ const COLORS: &[(u8, u8, u8)] = &[
     // (160, 95, 242),
 ];
 
-fn main() {
+struct BlockGame {
     // The key is the player id and the pointer id.
-    let mut player_pointers: HashMap<(u32, u32), PlayerPointerInfo> = HashMap::new();
-    let mut player_colors = HashMap::new();
-
-    let mut rapier = RapierIntegration::new();
-    // let random = oorandom::Rand32::new(14);
-    let mut physics_objects = Vec::new();
-
-    let mut random = oorandom::Rand32::new(19);
-    /*
-    physics_objects.push(add_polyline(
-        &mut rapier,
-        3.0,
-        5.0,
-        &[Vec2::ZERO, Vec2::ONE, Vec2::X],
-    ));
-    */
-    // physics_objects.push(add_ball(&mut rapier, &mut random, 0.5, 0.5, 0.1));
-
-    let floor_half_depth = 0.02;
-    let floor_height = 1.5 - floor_half_depth;
-    physics_objects.push(add_rectangle(
-        &mut rapier,
-        &mut random,
-        0.5,
-        floor_height + floor_half_depth,
-        4.0,
-        floor_half_depth,
-        true,
-        Some((222, 175, 166)),
-    ));
-
-    let size = 0.1;
-    let padding = 0.01;
-    for i in 0..3 {
-        for j in 0..3 {
+    player_pointers: DeterministicMap<(u32, u32), PlayerPointerInfo>,
+    player_colors: DeterministicMap<u32, (u8, u8, u8)>,
+    rapier: RapierIntegration,
+    physics_objects: Vec<PhysicsObject>,
+    cameras: DeterministicMap<u32, Camera>,
+    gestures: GestureRecognizer,
+    actions: ActionMap<Action>,
+    /// Where each player's pointer was last seen, in world coordinates.
+    player_cursors: DeterministicMap<u32, Vec2>,
+}
+
+register_game!(BlockGame);
+
+impl Game for BlockGame {
+    fn init() -> Self {
+        let mut rapier = RapierIntegration::new();
+        let mut physics_objects = Vec::new();
+
+        /*
+        physics_objects.push(add_polyline(
+            &mut rapier,
+            3.0,
+            5.0,
+            &[Vec2::ZERO, Vec2::ONE, Vec2::X],
+        ));
+        */
+        // physics_objects.push(add_ball(&mut rapier, 0.5, 0.5, 0.1));
+
+        let floor_half_depth = 0.02;
+        let floor_height = 1.5 - floor_half_depth;
+        physics_objects.push(add_rectangle(
+            &mut rapier,
+            0.5,
+            floor_height + floor_half_depth,
+            4.0,
+            floor_half_depth,
+            true,
+            Some((222, 175, 166)),
+        ));
+
+        let size = 0.1;
+        let padding = 0.01;
+        for i in 0..3 {
+            for j in 0..3 {
+                physics_objects.push(add_rectangle(
+                    &mut rapier,
+                    0.8 + i as f32 * (size * 2.0 + padding),
+                    floor_height - j as f32 * (size * 2.0 + padding),
+                    size,
+                    size,
+                    false,
+                    None,
+                ));
+            }
+        }
+
+        /*
+        for j in 0..5 {
+            physics_objects.push(add_rectangle(
+                &mut rapier,
+                1.8,
+                j as f32 * 0.06,
+                0.05,
+                0.05,
+                false,
+            ));
+        }
+        */
+        for j in 0..4 {
             physics_objects.push(add_rectangle(
                 &mut rapier,
-                &mut random,
-                0.8 + i as f32 * (size * 2.0 + padding),
-                floor_height - j as f32 * (size * 2.0 + padding),
-                size,
-                size,
+                1.8,
+                j as f32 * 0.2,
+                0.3,
+                0.05,
                 false,
                 None,
             ));
         }
-    }
 
-    /*
-    for j in 0..5 {
-        physics_objects.push(add_rectangle(
-            &mut rapier,
-            &mut random,
-            1.8,
-            j as f32 * 0.06,
-            0.05,
-            0.05,
-            false,
-        ));
-    }
-    */
-    for j in 0..4 {
-        physics_objects.push(add_rectangle(
-            &mut rapier,
-            &mut random,
-            1.8,
-            j as f32 * 0.2,
-            0.3,
-            0.05,
-            false,
-            None,
-        ));
-    }
+        for _ in 0..5 {
+            physics_objects.push(add_shape(&mut rapier, 2.0, 0.5, 3, 0.12));
+        }
 
-    for _ in 0..5 {
-        physics_objects.push(add_shape(&mut rapier, &mut random, 2.0, 0.5, 3, 0.12));
-    }
+        for _ in 0..3 {
+            physics_objects.push(add_ball(&mut rapier, 0.4, 0.5, 0.1));
+        }
 
-    for _ in 0..3 {
-        physics_objects.push(add_ball(&mut rapier, &mut random, 0.4, 0.5, 0.1));
+        Self {
+            player_pointers: DeterministicMap::new(),
+            player_colors: DeterministicMap::new(),
+            rapier,
+            physics_objects,
+            cameras: DeterministicMap::new(),
+            gestures: GestureRecognizer::new(),
+            actions: default_actions(),
+            player_cursors: DeterministicMap::new(),
+        }
     }
 
-    mini_engine::run(move |event| match event {
-        Event::FixedUpdate => {
-            for pointer in player_pointers.values() {
-                if let Some(collider) = pointer.moving_collider {
-                    let collider = rapier.collider_set.get(collider).unwrap();
-                    let rigid_body = rapier
-                        .rigid_body_set
-                        .get_mut(collider.parent().unwrap())
-                        .unwrap();
+    fn fixed_update(&mut self) {
+        for object in self.physics_objects.iter_mut() {
+            object.previous_position =
+                *self.rapier.rigid_body_set[object.rigid_body_handle].position();
+        }
 
-                    let p = pointer.cursor_position + pointer.offset;
-                    rigid_body.set_translation([p.x, p.y].into(), true);
+        let players: Vec<u32> = self.player_colors.keys().copied().collect();
+        for player in players {
+            if let Some(&cursor) = self.player_cursors.get(&player) {
+                if self.actions.is_just_pressed(player, Action::Delete) {
+                    self.delete_body_at(cursor);
+                }
+                if self.actions.is_just_pressed(player, Action::Spawn) {
+                    self.spawn_body_at(cursor);
                 }
             }
-            rapier.step();
+            let rotation = self.actions.value(player, Action::Rotate);
+            if rotation != 0.0 {
+                self.rotate_held_bodies(player, rotation);
+            }
         }
-        Event::Draw => {
-            for PhysicsObject {
-                rigid_body_handle,
-                color,
-            } in physics_objects.iter()
+
+        for (&(player, pointer_id), pointer) in self.player_pointers.iter() {
+            if let (Some(collider), Some(state)) =
+                (pointer.moving_collider, pointer_state(player, pointer_id))
             {
-                let rigid_body = rapier.rigid_body_set.get(*rigid_body_handle).unwrap();
-                for collider in rigid_body.colliders() {
-                    let collider = rapier.collider_set.get(*collider).unwrap();
-                    let shape = collider.shape();
-
-                    let matrix = collider.position().to_matrix();
-                    let matrix = matrix.scale(1.0 / WORLD_SCALE_FACTOR);
-                    set_transform(
-                        matrix[0], matrix[1], matrix[3], matrix[4], matrix[6], matrix[7],
-                    );
-                    set_color(color.0, color.1, color.2, 255);
-                    match shape.shape_type() {
-                        ShapeType::Ball => {
-                            let ball = shape.as_ball().unwrap();
-
-                            draw_circle(0.0, 0.0, ball.radius);
-                        }
-                        ShapeType::Cuboid => {
-                            let rect = shape.as_cuboid().unwrap();
-                            let extents = rect.half_extents;
+                let collider = self.rapier.collider_set.get(collider).unwrap();
+                let rigid_body = self
+                    .rapier
+                    .rigid_body_set
+                    .get_mut(collider.parent().unwrap())
+                    .unwrap();
+
+                let p = Vec2::new(state.position[0], state.position[1]) + pointer.offset;
+                rigid_body.set_translation([p.x, p.y].into(), true);
+            }
+        }
+        self.rapier.step();
 
-                            draw_rect(-extents.x, -extents.y, extents.x * 2.0, extents.y * 2.0);
-                        }
-                        ShapeType::ConvexPolygon => {
-                            let convex_polygon = shape.as_convex_polygon().unwrap();
-                            convex_polygon.points();
-                            begin_path();
-                            let points = convex_polygon.points();
-                            move_to(points[0].x, points[0].y);
-                            for p in &convex_polygon.points()[1..] {
-                                line_to(p.x, p.y);
-                            }
-                            line_to(points[0].x, points[0].y);
-
-                            fill();
-                        }
-                        _ => {
-                            log(&format!("Unexpected shape type: {:?}", shape.shape_type()));
+        for gesture in self.gestures.fixed_update() {
+            self.on_gesture(gesture);
+        }
+    }
+
+    fn draw(&mut self) {
+        // Used to outline bodies that are being held.
+        set_line_width(SELECTION_OUTLINE_WIDTH);
+        set_line_join(LineJoin::Round);
+        set_line_cap(LineCap::Round);
+
+        // Everything below is drawn in world units.
+        let camera = local_player()
+            .and_then(|player| self.cameras.get(&player).copied())
+            .unwrap_or_else(|| new_camera(local_viewport().unwrap_or(DEFAULT_VIEWPORT)));
+        camera.apply();
+
+        let alpha = interpolation_alpha();
+        for PhysicsObject {
+            rigid_body_handle,
+            color,
+            previous_position,
+        } in self.physics_objects.iter()
+        {
+            let rigid_body = self.rapier.rigid_body_set.get(*rigid_body_handle).unwrap();
+            let body_position = interpolate(previous_position, rigid_body.position(), alpha);
+            for collider_handle in rigid_body.colliders() {
+                let collider = self.rapier.collider_set.get(*collider_handle).unwrap();
+                let shape = collider.shape();
+
+                let position = match collider.position_wrt_parent() {
+                    Some(offset) => body_position * offset,
+                    None => body_position,
+                };
+                push_transform();
+                translate(position.translation.vector.x, position.translation.vector.y);
+                rotate(position.rotation.angle());
+                set_color(color.0, color.1, color.2, 255);
+                match shape.shape_type() {
+                    ShapeType::Ball => {
+                        let ball = shape.as_ball().unwrap();
+
+                        draw_circle(0.0, 0.0, ball.radius);
+                    }
+                    ShapeType::Cuboid => {
+                        let rect = shape.as_cuboid().unwrap();
+                        let extents = rect.half_extents;
+
+                        draw_rect(-extents.x, -extents.y, extents.x * 2.0, extents.y * 2.0);
+                    }
+                    ShapeType::ConvexPolygon => {
+                        let convex_polygon = shape.as_convex_polygon().unwrap();
+                        begin_path();
+                        let points = convex_polygon.points();
+                        move_to(points[0].x, points[0].y);
+                        for p in &convex_polygon.points()[1..] {
+                            line_to(p.x, p.y);
                         }
+                        close_path();
+                        fill();
+                    }
+                    _ => {
+                        log(&format!("Unexpected shape type: {:?}", shape.shape_type()));
                     }
-                    reset_transform();
                 }
-            }
 
-            for pointer in player_pointers.values() {
-                if pointer.render {
-                    let p = pointer.cursor_position / WORLD_SCALE_FACTOR;
-                    let (radius, alpha) = if pointer.cursor_down {
-                        (0.02, 255)
-                    } else {
-                        (0.03, 150)
-                    };
-                    set_color(pointer.color.0, pointer.color.1, pointer.color.2, alpha);
-                    draw_circle(p.x, p.y, radius / WORLD_SCALE_FACTOR);
+                // The shape's path is still current, so it can be outlined with the holder's color.
+                if let Some(pointer) = self
+                    .player_pointers
+                    .values()
+                    .find(|pointer| pointer.moving_collider == Some(*collider_handle))
+                {
+                    set_color(pointer.color.0, pointer.color.1, pointer.color.2, 255);
+                    stroke();
                 }
+                pop_transform();
             }
         }
-        Event::PlayerJoined { player } => {
-            log(&format!("Player Joined: {:?}", player));
-            player_colors.insert(
-                player,
-                COLORS[random.rand_range(0..COLORS.len() as _) as usize],
-            );
+
+        for (&(player, pointer_id), pointer) in self.player_pointers.iter() {
+            let Some(state) = pointer_state(player, pointer_id) else {
+                continue;
+            };
+            if is_cursor_visible(&state) {
+                let [x, y] = state.position;
+                let (radius, alpha) = if state.is_down {
+                    (0.02, 255)
+                } else {
+                    (0.03, 150)
+                };
+                set_color(pointer.color.0, pointer.color.1, pointer.color.2, alpha);
+                draw_circle(x, y, radius);
+            }
         }
-        Event::PlayerLeft { player } => {
-            log(&format!("Player left: {:?}", player));
-            player_colors.remove(&player);
-
-            let mut to_remove = Vec::new();
-            for key in player_pointers.keys() {
-                if key.0 == player {
-                    to_remove.push(*key);
-                }
+    }
+
+    fn on_event(&mut self, event: &Event) {
+        self.actions.handle(event);
+    }
+
+    fn camera(&self, player: u32) -> Option<&Camera> {
+        self.cameras.get(&player)
+    }
+
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        for object in &self.physics_objects {
+            let body = &self.rapier.rigid_body_set[object.rigid_body_handle];
+            object.rigid_body_handle.into_raw_parts().hash(hasher);
+            let translation = body.translation();
+            let linvel = body.linvel();
+            for value in [
+                translation.x,
+                translation.y,
+                body.rotation().angle(),
+                linvel.x,
+                linvel.y,
+                body.angvel(),
+            ] {
+                hasher.write_f32(value);
             }
-            for key in to_remove {
-                player_pointers.remove(&key);
+        }
+
+        for (key, pointer) in &self.player_pointers {
+            key.hash(hasher);
+            let moving_collider = pointer.moving_collider.map(|c| c.into_raw_parts());
+            moving_collider.hash(hasher);
+            hasher.write_f32(pointer.offset.x);
+            hasher.write_f32(pointer.offset.y);
+        }
+
+        // Pointer positions are converted to the world with each player's camera, viewport included.
+        for (player, camera) in &self.cameras {
+            player.hash(hasher);
+            let viewport = camera.viewport;
+            for value in [
+                camera.center[0],
+                camera.center[1],
+                camera.zoom,
+                viewport.width,
+                viewport.height,
+                viewport.device_pixel_ratio,
+            ] {
+                hasher.write_f32(value);
             }
         }
-        Event::PointerMove {
-            player,
-            pointer_id,
-            x,
-            y,
-        } => {
-            if let Some(player_color) = player_colors.get(&player) {
-                let entry = player_pointers.entry((player, pointer_id));
-                let pointer = entry.or_insert_with(|| PlayerPointerInfo::new(*player_color));
-
-                let world_position = Vec2::new(x, y) * WORLD_SCALE_FACTOR;
-                pointer.last_cursor_position = pointer.cursor_position;
-                pointer.cursor_position = world_position;
+    }
+
+    fn on_resize(&mut self, player: u32, viewport: Viewport) {
+        if let Some(camera) = self.cameras.get_mut(&player) {
+            camera.viewport = viewport;
+        }
+    }
+
+    fn on_player_joined(&mut self, player: u32) {
+        log(&format!("Player Joined: {:?}", player));
+        self.player_colors.insert(player, random_color());
+        let viewport = viewport(player).unwrap_or(DEFAULT_VIEWPORT);
+        self.cameras.insert(player, new_camera(viewport));
+    }
+
+    fn on_player_left(&mut self, player: u32) {
+        log(&format!("Player left: {:?}", player));
+        self.player_colors.remove(&player);
+        self.cameras.remove(&player);
+        self.gestures.remove_player(player);
+        self.player_cursors.remove(&player);
+
+        let mut to_remove = Vec::new();
+        for key in self.player_pointers.keys() {
+            if key.0 == player {
+                to_remove.push(*key);
             }
         }
-        Event::PointerDown {
-            player,
-            pointer_id,
-            x,
-            y,
-        } => {
-            if let Some(player_color) = player_colors.get(&player) {
-                let entry = player_pointers.entry((player, pointer_id));
-                let pointer = entry.or_insert_with(|| PlayerPointerInfo::new(*player_color));
-
-                pointer.render = true;
-                pointer.cursor_down = true;
-                let world_position = Vec2::new(x, y) * WORLD_SCALE_FACTOR;
-                pointer.cursor_position = world_position;
-                if let Some((collider_handle, position)) = rapier.query_pipeline.project_point(
-                    &rapier.rigid_body_set,
-                    &rapier.collider_set,
-                    &[world_position.x, world_position.y].into(),
-                    true,
-                    QueryFilter::only_dynamic(),
-                ) {
-                    let collider = rapier.collider_set.get(collider_handle).unwrap();
-                    let rigid_body = rapier
-                        .rigid_body_set
-                        .get_mut(collider.parent().unwrap())
-                        .unwrap();
+        for key in to_remove {
+            self.player_pointers.remove(&key);
+        }
+    }
+
+    fn on_wheel(&mut self, event: WheelEvent) {
+        // The wheel turns held bodies through `Action::Rotate` instead.
+        if self.is_holding(event.player) {
+            return;
+        }
+        if let Some(camera) = self.cameras.get_mut(&event.player) {
+            let [_, delta] = event.pixel_delta();
+            let factor = libm::expf(-delta * WHEEL_ZOOM_SPEED);
+            camera.zoom_at(event.screen_x, event.screen_y, factor);
+        }
+    }
 
-                    let rigid_body_position = rigid_body.translation();
-                    if position.is_inside {
-                        pointer.moving_collider = Some(collider_handle);
-                        pointer.offset = Vec2::new(rigid_body_position.x, rigid_body_position.y)
-                            - world_position;
-                        rigid_body.set_gravity_scale(0.0, true);
-                        rigid_body.set_angvel(0.0, false);
-                        rigid_body.set_angular_damping(0.99);
+    fn on_pointer(&mut self, event: PointerEvent) {
+        let Some(player_color) = self.player_colors.get(&event.player) else {
+            return;
+        };
+        let gestures = self.gestures.pointer(&event);
+        let grab = self
+            .actions
+            .is_bound(Action::Grab, Binding::PointerButton(event.details.button));
+        let world_position = Vec2::new(event.x, event.y);
+        self.player_cursors.insert(event.player, world_position);
+
+        let entry = self.player_pointers.entry((event.player, event.pointer_id));
+        let pointer = entry.or_insert_with(|| PlayerPointerInfo::new(*player_color));
+
+        match event.phase {
+            PointerPhase::Move => {}
+            PointerPhase::Down => {
+                if grab {
+                    if let Some((collider_handle, position)) =
+                        self.rapier.query_pipeline.project_point(
+                            &self.rapier.rigid_body_set,
+                            &self.rapier.collider_set,
+                            &[world_position.x, world_position.y].into(),
+                            true,
+                            QueryFilter::only_dynamic(),
+                        )
+                    {
+                        let collider = self.rapier.collider_set.get(collider_handle).unwrap();
+                        let rigid_body = self
+                            .rapier
+                            .rigid_body_set
+                            .get_mut(collider.parent().unwrap())
+                            .unwrap();
+
+                        let rigid_body_position = rigid_body.translation();
+                        if position.is_inside {
+                            pointer.moving_collider = Some(collider_handle);
+                            pointer.offset =
+                                Vec2::new(rigid_body_position.x, rigid_body_position.y)
+                                    - world_position;
+                            rigid_body.set_gravity_scale(0.0, true);
+                            rigid_body.set_angvel(0.0, false);
+                            rigid_body.set_angular_damping(0.99);
+                        }
                     }
                 }
             }
-        }
-        Event::PointerUp {
-            player,
-            pointer_id,
-            is_mouse,
-            x: _,
-            y: _,
-        } => {
-            if let Some(player_color) = player_colors.get(&player) {
-                let entry = player_pointers.entry((player, pointer_id));
-                let pointer = entry.or_insert_with(|| PlayerPointerInfo::new(*player_color));
-
-                // Do not render touch or stylus events that are no longer occurring.
-                pointer.render = is_mouse;
-                pointer.cursor_down = false;
+            PointerPhase::Up | PointerPhase::Cancel => {
                 if let Some(collider) = pointer.moving_collider {
-                    let collider = rapier.collider_set.get(collider).unwrap();
-                    let rigid_body = rapier
+                    let collider = self.rapier.collider_set.get(collider).unwrap();
+                    let rigid_body = self
+                        .rapier
                         .rigid_body_set
                         .get_mut(collider.parent().unwrap())
                         .unwrap();
 
-                    let velocity = (pointer.cursor_position - pointer.last_cursor_position) * 30.0;
-                    rigid_body.set_linvel([velocity.x, velocity.y].into(), true);
+                    // Only throw the body if it was released on purpose.
+                    if event.phase == PointerPhase::Up {
+                        let [x, y] = pointer_state(event.player, event.pointer_id)
+                            .map_or([0.0, 0.0], |state| state.velocity);
+                        // The pointer's velocity is per fixed update, but Rapier's is per second.
+                        let velocity = Vec2::new(x, y) * (THROW_SPEED_SCALE / time().fixed_delta);
+                        rigid_body.set_linvel([velocity.x, velocity.y].into(), true);
+                    }
                     rigid_body.set_gravity_scale(1.0, true);
                     rigid_body.set_angular_damping(0.2);
                     pointer.moving_collider = None;
                 }
             }
         }
-    });
+
+        for gesture in gestures {
+            self.on_gesture(gesture);
+        }
+    }
 }
 
-fn add_ball(
-    rapier: &mut RapierIntegration,
-    random: &mut oorandom::Rand32,
-    x: f32,
-    y: f32,
-    radius: f32,
-) -> PhysicsObject {
+impl BlockGame {
+    /// Two fingers turn the block held by one of them, or otherwise pan and zoom the view.
+    fn on_gesture(&mut self, gesture: Gesture) {
+        match gesture {
+            Gesture::Rotate { player, angle, .. } => {
+                self.rotate_held_bodies(player, angle);
+            }
+            Gesture::Pinch {
+                player,
+                center,
+                scale,
+            } => {
+                if !self.is_holding(player) {
+                    if let Some(camera) = self.cameras.get_mut(&player) {
+                        camera.zoom_at(center[0], center[1], scale);
+                    }
+                }
+            }
+            Gesture::Pan { player, delta } => {
+                if !self.is_holding(player) {
+                    if let Some(camera) = self.cameras.get_mut(&player) {
+                        camera.pan_screen(delta[0], delta[1]);
+                    }
+                }
+            }
+            Gesture::DoubleTap { player, .. } => {
+                // Brings back a view that was panned or zoomed too far.
+                if let Some(camera) = self.cameras.get_mut(&player) {
+                    *camera = new_camera(camera.viewport);
+                }
+            }
+            Gesture::LongPress { .. } => {}
+        }
+    }
+
+    fn spawn_body_at(&mut self, position: Vec2) {
+        let object = if random_range(RandomStream::Simulation, 0..4) == 0 {
+            add_ball(&mut self.rapier, position.x, position.y, 0.1)
+        } else {
+            let sides = random_range(RandomStream::Simulation, 3..7) as u8;
+            add_shape(&mut self.rapier, position.x, position.y, sides, 0.12)
+        };
+        self.physics_objects.push(object);
+    }
+
+    /// Removes the body under `position`, letting go of it first if anyone is holding it.
+    fn delete_body_at(&mut self, position: Vec2) {
+        let Some((collider_handle, projection)) = self.rapier.query_pipeline.project_point(
+            &self.rapier.rigid_body_set,
+            &self.rapier.collider_set,
+            &[position.x, position.y].into(),
+            true,
+            QueryFilter::only_dynamic(),
+        ) else {
+            return;
+        };
+        if !projection.is_inside {
+            return;
+        }
+        let rigid_body_handle = self.rapier.collider_set[collider_handle].parent().unwrap();
+
+        for pointer in self.player_pointers.values_mut() {
+            if pointer.moving_collider == Some(collider_handle) {
+                pointer.moving_collider = None;
+            }
+        }
+        self.physics_objects
+            .retain(|object| object.rigid_body_handle != rigid_body_handle);
+        self.rapier.remove(rigid_body_handle);
+    }
+
+    fn is_holding(&self, player: u32) -> bool {
+        self.player_pointers
+            .iter()
+            .any(|((p, _), pointer)| *p == player && pointer.moving_collider.is_some())
+    }
+
+    /// Turns every body `player` holds by `angle` radians around the pointer holding it.
+    fn rotate_held_bodies(&mut self, player: u32, angle: f32) {
+        for ((p, _), pointer) in self.player_pointers.iter_mut() {
+            if *p != player {
+                continue;
+            }
+            let Some(collider) = pointer.moving_collider else {
+                continue;
+            };
+            let collider = self.rapier.collider_set.get(collider).unwrap();
+            let rigid_body = self
+                .rapier
+                .rigid_body_set
+                .get_mut(collider.parent().unwrap())
+                .unwrap();
+
+            // Turn around the pointer instead of the body's center.
+            let (sin, cos) = libm::sincosf(angle);
+            let offset = pointer.offset;
+            pointer.offset = Vec2::new(
+                offset.x * cos - offset.y * sin,
+                offset.x * sin + offset.y * cos,
+            );
+            let position = *rigid_body.position();
+            let rotation = position.rotation.angle() + angle;
+            rigid_body.set_position(Isometry::new(position.translation.vector, rotation), true);
+        }
+    }
+}
+
+/// Blends from `from` to `to`, turning whichever way around is shorter.
+fn interpolate(from: &Isometry<Real>, to: &Isometry<Real>, alpha: f32) -> Isometry<Real> {
+    let translation = from.translation.vector.lerp(&to.translation.vector, alpha);
+    let angle = from.rotation.angle() + from.rotation.angle_to(&to.rotation) * alpha;
+    Isometry::new(translation, angle)
+}
+
+/// Touches and pens are only shown while they are down, but a mouse keeps hovering after it is released.
+fn is_cursor_visible(state: &PointerState) -> bool {
+    state.is_down || state.kind == PointerKind::Mouse
+}
+
+/// Every player sees the same part of the world, scaled to fit their canvas.
+fn new_camera(viewport: Viewport) -> Camera {
+    let fit = Fit::Contain {
+        width: VIEW_WIDTH,
+        height: VIEW_HEIGHT,
+    };
+    Camera::new([VIEW_WIDTH / 2.0, VIEW_HEIGHT / 2.0], fit, viewport)
+}
+
+fn random_color() -> (u8, u8, u8) {
+    COLORS[random_range(RandomStream::Cosmetic, 0..COLORS.len() as u32) as usize]
+}
+
+fn add_ball(rapier: &mut RapierIntegration, x: f32, y: f32, radius: f32) -> PhysicsObject {
     let rigid_body = RigidBodyBuilder::dynamic()
         .translation(vector![x as _, y as _])
         .linear_damping(1.4)
@@ -334,13 +644,13 @@ fn add_ball(
         .insert_with_parent(collider, ball_body_handle, &mut rapier.rigid_body_set);
     PhysicsObject {
         rigid_body_handle: ball_body_handle,
-        color: COLORS[random.rand_range(0..COLORS.len() as _) as usize],
+        color: random_color(),
+        previous_position: *rapier.rigid_body_set[ball_body_handle].position(),
     }
 }
 
 fn add_shape(
     rapier: &mut RapierIntegration,
-    random: &mut oorandom::Rand32,
     x: f32,
     y: f32,
     sides: u8,
@@ -350,13 +660,15 @@ fn add_shape(
 
     for i in 0..sides {
         let angle = (i as f32 / sides as f32) * std::f32::consts::TAU;
-        let (sin, cos) = angle.sin_cos();
+        let (sin, cos) = libm::sincosf(angle);
         points.push(Vec2::new(sin, cos) * side_size);
     }
 
+    let rigid_body_handle = add_convex_hull(rapier, x, y, &points);
     PhysicsObject {
-        rigid_body_handle: add_convex_hull(rapier, x, y, &points),
-        color: COLORS[random.rand_range(0..COLORS.len() as _) as usize],
+        rigid_body_handle,
+        color: random_color(),
+        previous_position: *rapier.rigid_body_set[rigid_body_handle].position(),
     }
 }
 
@@ -386,7 +698,6 @@ fn add_convex_hull(
 
 fn add_rectangle(
     rapier: &mut RapierIntegration,
-    random: &mut oorandom::Rand32,
     x: f32,
     y: f32,
     half_width: f32,
@@ -416,10 +727,13 @@ fn add_rectangle(
 
     PhysicsObject {
         rigid_body_handle: body_handle,
-        color: color.unwrap_or_else(|| COLORS[random.rand_range(0..COLORS.len() as _) as usize]),
+        color: color.unwrap_or_else(random_color),
+        previous_position: *rapier.rigid_body_set[body_handle].position(),
     }
 }
 
+/// Rapier is built with `enhanced-determinism`, so stepping gives the same result on every platform.
+/// Math that feeds into the simulation uses `libm` instead of `f32` methods for the same reason.
 pub struct RapierIntegration {
     gravity: Vec2,
     integration_parameters: IntegrationParameters,
@@ -462,9 +776,19 @@ impl RapierIntegration {
             &mut self.multibody_joint_set,
             true,
         );
+
+        // Rebuilt so queries made before the next step cannot find the removed colliders.
+        self.query_pipeline = QueryPipeline::new();
+        self.query_pipeline.update(
+            &self.island_manager,
+            &self.rigid_body_set,
+            &self.collider_set,
+        );
     }
 
+    /// Advances the simulation by `Time::fixed_delta`.
     pub fn step(&mut self) {
+        self.integration_parameters.dt = time().fixed_delta;
         let gravity: [f32; 2] = self.gravity.into();
         let gravity = gravity.into();
         self.physics_pipeline.step(
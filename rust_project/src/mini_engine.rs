@@ -1,6 +1,48 @@
+//! A tiny engine that abstracts interacting with the host and running a `Game`.
+
+mod actions;
+mod camera;
+mod collections;
+pub mod draw_list;
+mod gamepad;
+mod gestures;
+mod image;
+mod input;
+mod keyboard;
+mod random;
+mod state_hash;
+mod text;
+mod time;
+mod transform;
+mod viewport;
+pub use actions::*;
+pub use camera::*;
+pub use collections::*;
+use draw_list::record;
+pub use draw_list::{DrawCommand, DrawList, LineCap, LineJoin};
+pub use draw_list::{TextAlign, TextBaseline};
+pub use gamepad::*;
+pub use gestures::*;
+pub use image::*;
+pub use input::*;
+pub use keyboard::*;
+pub use random::*;
+pub use state_hash::*;
+pub use text::*;
+pub use time::*;
+pub use transform::*;
+pub use viewport::*;
+
+#[derive(Clone, Copy, Debug)]
 pub enum Event {
     FixedUpdate,
-    Draw,
+    /// Draws what `player` sees. Sent separately by every player's host, and never shared.
+    ///
+    /// `alpha` is how far the host's clock is between the last fixed update and the next one, from 0 to 1.
+    Draw {
+        player: u32,
+        alpha: f32,
+    },
     PlayerJoined {
         player: u32,
     },
@@ -12,171 +54,960 @@ pub enum Event {
         pointer_id: u32,
         x: f32,
         y: f32,
+        details: PointerDetails,
     },
     PointerDown {
         player: u32,
         pointer_id: u32,
         x: f32,
         y: f32,
+        details: PointerDetails,
     },
     PointerUp {
         player: u32,
         pointer_id: u32,
-        is_mouse: bool,
+        x: f32,
+        y: f32,
+        details: PointerDetails,
+    },
+    /// The host stopped tracking a pointer without it being released,
+    /// for example when the OS takes over a touch for scrolling.
+    PointerCancel {
+        player: u32,
+        pointer_id: u32,
+        x: f32,
+        y: f32,
+        details: PointerDetails,
+    },
+    KeyDown {
+        player: u32,
+        key: Key,
+    },
+    KeyUp {
+        player: u32,
+        key: Key,
+    },
+    ImageLoaded {
+        image: ImageHandle,
+        width: u32,
+        height: u32,
+    },
+    /// `x` and `y` are where the pointer is, like in pointer events.
+    Wheel {
+        player: u32,
+        delta_x: f32,
+        delta_y: f32,
+        delta_mode: DeltaMode,
         x: f32,
         y: f32,
     },
+    /// `width` and `height` are in CSS pixels.
+    Resize {
+        player: u32,
+        width: f32,
+        height: f32,
+        device_pixel_ratio: f32,
+    },
+    /// `gamepad` is the host's index for the gamepad, which is only unique per player.
+    GamepadConnected {
+        player: u32,
+        gamepad: u32,
+    },
+    GamepadDisconnected {
+        player: u32,
+        gamepad: u32,
+    },
+    /// `value` is how far the button is pressed, from 0 to 1.
+    GamepadButtonChanged {
+        player: u32,
+        gamepad: u32,
+        button: GamepadButton,
+        pressed: bool,
+        value: f32,
+    },
+    GamepadAxisChanged {
+        player: u32,
+        gamepad: u32,
+        axis: GamepadAxis,
+        value: f32,
+    },
+    /// Sent by the host so `Time::fixed_delta` matches how often it sends `FixedUpdate`.
+    SetFixedUpdateInterval {
+        seconds: f32,
+    },
+    /// Sent by the host so every peer in a session draws the same random numbers.
+    /// Only the first seed of a session is used, and `register_game!` builds the game when it arrives.
+    SetSessionSeed {
+        seed: u64,
+    },
+    /// Another peer's hash of its state after `tick`, sent to find desyncs.
+    StateHashReported {
+        player: u32,
+        tick: u64,
+        hash: u64,
+    },
 }
 
-fn send_event(event: Event) {
-    // This is safe because this Wasm program will only ever be single-threaded.
-    unsafe {
-        (PROGRAM_FUNCTION.as_mut().unwrap())(event);
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointerPhase {
+    Down,
+    Move,
+    Up,
+    Cancel,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PointerKind {
+    #[default]
+    Mouse,
+    Touch,
+    Pen,
+}
+
+impl PointerKind {
+    /// Converts the host's encoding of the DOM `PointerEvent.pointerType`. Unknown values are treated as a mouse.
+    pub fn from_u32(kind: u32) -> Self {
+        match kind {
+            1 => PointerKind::Touch,
+            2 => PointerKind::Pen,
+            _ => PointerKind::Mouse,
+        }
     }
 }
 
-#[no_mangle]
-extern "C" fn player_joined(player: u32) {
-    send_event(Event::PlayerJoined { player })
+/// A mouse button, numbered like the DOM `PointerEvent.button`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PointerButton {
+    #[default]
+    Primary,
+    Middle,
+    Secondary,
+    Back,
+    Forward,
 }
 
-#[no_mangle]
-extern "C" fn peer_left(player: u32) {
-    send_event(Event::PlayerLeft { player })
+impl PointerButton {
+    /// Converts a DOM `PointerEvent.button`. Moves report -1, which is treated as `Primary` like other unknown values.
+    pub fn from_dom(button: i32) -> Self {
+        match button {
+            1 => PointerButton::Middle,
+            2 => PointerButton::Secondary,
+            3 => PointerButton::Back,
+            4 => PointerButton::Forward,
+            _ => PointerButton::Primary,
+        }
+    }
 }
 
-#[no_mangle]
-extern "C" fn pointer_down(player: u32, pointer_id: u32, x: f32, y: f32) {
-    send_event(Event::PointerDown {
-        player,
-        pointer_id,
-        x: x,
-        y: y,
-    })
+/// Everything about a pointer other than its position, from the DOM `PointerEvent`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerDetails {
+    pub kind: PointerKind,
+    /// The button that was pressed or released. Touches and pens in contact count as `Primary`.
+    pub button: PointerButton,
+    /// From 0 to 1. Pointers without pressure sensing report 0.5 while pressed.
+    pub pressure: f32,
+    /// The pen's angle from vertical in degrees, from -90 to 90, along the x and y axes.
+    pub tilt_x: f32,
+    pub tilt_y: f32,
+    /// The size of the contact area in CSS pixels.
+    pub width: f32,
+    pub height: f32,
 }
 
-#[no_mangle]
-extern "C" fn pointer_move(player: u32, pointer_id: u32, x: f32, y: f32) {
-    log(&format!("POINTER ID: {:?}", pointer_id));
-    send_event(Event::PointerMove {
-        player,
-        pointer_id,
-        x: x,
-        y: y,
-    })
+impl Default for PointerDetails {
+    /// A mouse that is not pressed.
+    fn default() -> Self {
+        Self {
+            kind: PointerKind::Mouse,
+            button: PointerButton::Primary,
+            pressure: 0.0,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        }
+    }
+}
+
+/// A `PointerDown`, `PointerMove`, `PointerUp`, or `PointerCancel` event.
+#[derive(Clone, Copy, Debug)]
+pub struct PointerEvent {
+    pub player: u32,
+    pub pointer_id: u32,
+    pub phase: PointerPhase,
+    pub details: PointerDetails,
+    /// In world coordinates if `Game::camera` returns a camera for the player,
+    /// otherwise the same as `screen_x` and `screen_y`.
+    pub x: f32,
+    pub y: f32,
+    /// In CSS pixels from the top left of the player's canvas.
+    pub screen_x: f32,
+    pub screen_y: f32,
 }
 
-#[no_mangle]
-extern "C" fn pointer_up(player: u32, pointer_id: u32, is_mouse: bool, x: f32, y: f32) {
-    send_event(Event::PointerUp {
+/// The unit of a `WheelEvent`'s deltas, like the DOM `WheelEvent.deltaMode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaMode {
+    Pixel,
+    Line,
+    Page,
+}
+
+impl DeltaMode {
+    /// Converts a DOM `WheelEvent.deltaMode`. Unknown values are treated as pixels.
+    pub fn from_dom(delta_mode: u32) -> Self {
+        match delta_mode {
+            1 => DeltaMode::Line,
+            2 => DeltaMode::Page,
+            _ => DeltaMode::Pixel,
+        }
+    }
+
+    /// How many pixels one unit of a delta scrolls for `player`.
+    pub fn pixel_scale(self, player: u32) -> f32 {
+        match self {
+            DeltaMode::Pixel => 1.0,
+            DeltaMode::Line => PIXELS_PER_LINE,
+            DeltaMode::Page => viewport(player).map_or(800.0, |viewport| viewport.height),
+        }
+    }
+}
+
+/// Roughly how many pixels browsers scroll per line.
+const PIXELS_PER_LINE: f32 = 16.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct WheelEvent {
+    pub player: u32,
+    /// Positive values scroll right.
+    pub delta_x: f32,
+    /// Positive values scroll down, which is towards the user on most mice.
+    pub delta_y: f32,
+    pub delta_mode: DeltaMode,
+    /// In world coordinates if `Game::camera` returns a camera for the player,
+    /// otherwise the same as `screen_x` and `screen_y`.
+    pub x: f32,
+    pub y: f32,
+    pub screen_x: f32,
+    pub screen_y: f32,
+}
+
+impl WheelEvent {
+    /// Returns the deltas converted to pixels, so mice that scroll by lines and
+    /// trackpads that scroll by pixels can be handled the same way.
+    pub fn pixel_delta(&self) -> [f32; 2] {
+        let scale = self.delta_mode.pixel_scale(self.player);
+        [self.delta_x * scale, self.delta_y * scale]
+    }
+}
+
+/// The game logic driven by `mini_engine`.
+///
+/// Every method other than `init` has an empty default so a game only implements
+/// the events it cares about. Use `register_game!` to hook an implementation up to the host.
+pub trait Game: 'static {
+    fn init() -> Self
+    where
+        Self: Sized;
+    fn fixed_update(&mut self) {}
+    fn draw(&mut self) {}
+    /// Called with every event before the hook for that event, for example to feed an `ActionMap`.
+    fn on_event(&mut self, _event: &Event) {}
+    fn on_player_joined(&mut self, _player: u32) {}
+    fn on_player_left(&mut self, _player: u32) {}
+    fn on_pointer(&mut self, _event: PointerEvent) {}
+    fn on_key_down(&mut self, _player: u32, _key: Key) {}
+    fn on_key_up(&mut self, _player: u32, _key: Key) {}
+    fn on_wheel(&mut self, _event: WheelEvent) {}
+    fn on_image_loaded(&mut self, _image: ImageHandle) {}
+    fn on_resize(&mut self, _player: u32, _viewport: Viewport) {}
+    fn on_gamepad_connected(&mut self, _player: u32, _gamepad: u32) {}
+    fn on_gamepad_disconnected(&mut self, _player: u32, _gamepad: u32) {}
+    /// Only called when a button is pressed or released. Analog values are available from `gamepad`.
+    fn on_gamepad_button(
+        &mut self,
+        _player: u32,
+        _gamepad: u32,
+        _button: GamepadButton,
+        _pressed: bool,
+    ) {
+    }
+    fn on_gamepad_axis(&mut self, _player: u32, _gamepad: u32, _axis: GamepadAxis, _value: f32) {}
+
+    /// The camera `player` sees the world through. If this returns a camera,
+    /// pointer events from `player` are converted to world coordinates before `on_pointer`.
+    fn camera(&self, _player: u32) -> Option<&Camera> {
+        None
+    }
+
+    /// Writes everything that affects the simulation to `hasher`, in the same order on every peer.
+    /// Used by `state_hash` to notice when peers stop agreeing.
+    fn hash_state(&self, _hasher: &mut StateHasher) {}
+}
+
+fn to_world(game: &impl Game, player: u32, screen_x: f32, screen_y: f32) -> [f32; 2] {
+    match game.camera(player) {
+        Some(camera) => camera.screen_to_world(screen_x, screen_y),
+        None => [screen_x, screen_y],
+    }
+}
+
+fn pointer_event(
+    game: &impl Game,
+    player: u32,
+    pointer_id: u32,
+    phase: PointerPhase,
+    screen_x: f32,
+    screen_y: f32,
+    details: PointerDetails,
+) -> PointerEvent {
+    let [x, y] = to_world(game, player, screen_x, screen_y);
+    PointerEvent {
         player,
         pointer_id,
-        is_mouse,
-        x: x,
-        y: y,
-    })
+        phase,
+        details,
+        x,
+        y,
+        screen_x,
+        screen_y,
+    }
 }
 
-#[no_mangle]
-extern "C" fn fixed_update() {
-    send_event(Event::FixedUpdate)
+/// Like `dispatch`, but builds the game when the session seed arrives, so `Game::init` can draw
+/// random numbers. Every peer ignores the events that arrive before it.
+pub fn dispatch_or_init<G: Game>(game: &mut Option<G>, event: Event) {
+    if game.is_none() {
+        let Event::SetSessionSeed { seed } = event else {
+            return;
+        };
+        set_session_seed(seed);
+        *game = Some(G::init());
+    }
+    if let Some(game) = game {
+        dispatch(game, event);
+    }
 }
 
-#[no_mangle]
-extern "C" fn draw() {
-    send_event(Event::Draw)
+/// Updates `mini_engine`'s own state for an event and then passes it to the game.
+pub fn dispatch<G: Game>(game: &mut G, event: Event) {
+    game.on_event(&event);
+    match event {
+        Event::FixedUpdate => {
+            game.fixed_update();
+            input::end_fixed_update();
+            advance_time();
+            record_state_hash(state_hash(game));
+            unsafe {
+                unsafe_external::external_fixed_update(time().tick as u32);
+            }
+        }
+        Event::Draw { player, alpha } => {
+            set_local_player(player);
+            set_interpolation_alpha(alpha);
+            text::reset_font();
+            transform::reset_transform_stack();
+            game.draw();
+            draw_list::submit();
+        }
+        Event::PlayerJoined { player } => {
+            image::request_images();
+            game.on_player_joined(player)
+        }
+        Event::PlayerLeft { player } => {
+            remove_player_viewport(player);
+            remove_player_gamepads(player);
+            remove_player_input(player);
+            game.on_player_left(player)
+        }
+        Event::PointerDown {
+            player,
+            pointer_id,
+            x,
+            y,
+            details,
+        } => {
+            let phase = PointerPhase::Down;
+            let event = pointer_event(game, player, pointer_id, phase, x, y, details);
+            record_pointer(&event);
+            game.on_pointer(event)
+        }
+        Event::PointerMove {
+            player,
+            pointer_id,
+            x,
+            y,
+            details,
+        } => {
+            let phase = PointerPhase::Move;
+            let event = pointer_event(game, player, pointer_id, phase, x, y, details);
+            record_pointer(&event);
+            game.on_pointer(event)
+        }
+        Event::PointerUp {
+            player,
+            pointer_id,
+            x,
+            y,
+            details,
+        } => {
+            let phase = PointerPhase::Up;
+            let event = pointer_event(game, player, pointer_id, phase, x, y, details);
+            record_pointer(&event);
+            game.on_pointer(event)
+        }
+        Event::PointerCancel {
+            player,
+            pointer_id,
+            x,
+            y,
+            details,
+        } => {
+            let phase = PointerPhase::Cancel;
+            let event = pointer_event(game, player, pointer_id, phase, x, y, details);
+            record_pointer(&event);
+            game.on_pointer(event)
+        }
+        Event::KeyDown { player, key } => {
+            // Ignore presses for keys that are already held so games only see the initial press.
+            if record_key(player, key, true) {
+                game.on_key_down(player, key)
+            }
+        }
+        Event::KeyUp { player, key } => {
+            if record_key(player, key, false) {
+                game.on_key_up(player, key)
+            }
+        }
+        Event::ImageLoaded {
+            image,
+            width,
+            height,
+        } => {
+            if set_image_loaded(image, width, height) {
+                game.on_image_loaded(image)
+            }
+        }
+        Event::Wheel {
+            player,
+            delta_x,
+            delta_y,
+            delta_mode,
+            x: screen_x,
+            y: screen_y,
+        } => {
+            let [x, y] = to_world(game, player, screen_x, screen_y);
+            game.on_wheel(WheelEvent {
+                player,
+                delta_x,
+                delta_y,
+                delta_mode,
+                x,
+                y,
+                screen_x,
+                screen_y,
+            })
+        }
+        Event::Resize {
+            player,
+            width,
+            height,
+            device_pixel_ratio,
+        } => {
+            let viewport = Viewport::new(width, height, device_pixel_ratio);
+            set_viewport(player, viewport);
+            game.on_resize(player, viewport)
+        }
+        Event::GamepadConnected { player, gamepad } => {
+            if connect_gamepad(player, gamepad) {
+                game.on_gamepad_connected(player, gamepad)
+            }
+        }
+        Event::GamepadDisconnected { player, gamepad } => {
+            if disconnect_gamepad(player, gamepad) {
+                game.on_gamepad_disconnected(player, gamepad)
+            }
+        }
+        Event::GamepadButtonChanged {
+            player,
+            gamepad,
+            button,
+            pressed,
+            value,
+        } => {
+            if set_gamepad_button(player, gamepad, button, pressed, value) {
+                game.on_gamepad_button(player, gamepad, button, pressed)
+            }
+        }
+        Event::GamepadAxisChanged {
+            player,
+            gamepad,
+            axis,
+            value,
+        } => {
+            if set_gamepad_axis(player, gamepad, axis, value) {
+                game.on_gamepad_axis(player, gamepad, axis, value)
+            }
+        }
+        Event::SetFixedUpdateInterval { seconds } => set_fixed_delta(seconds),
+        // The session is seeded by `dispatch_or_init` before the game is built.
+        Event::SetSessionSeed { .. } => {}
+        Event::StateHashReported { player, tick, hash } => check_state_hash(player, tick, hash),
+    }
 }
 
-static mut PROGRAM_FUNCTION: Option<Box<dyn FnMut(Event) + Sync>> = None;
+/// Stores a `Game` and generates the `main` function and the `#[no_mangle]` exports the host calls.
+///
+/// The game is constructed with `Game::init` once the host sends the session seed, see `dispatch_or_init`.
+#[macro_export]
+macro_rules! register_game {
+    ($game:ty) => {
+        thread_local! {
+            static GAME: std::cell::RefCell<Option<$game>> = const { std::cell::RefCell::new(None) };
+        }
 
-pub fn run(f: impl FnMut(Event) + 'static + Sync) {
-    setup_panic_hook();
-    unsafe {
-        PROGRAM_FUNCTION = Some(Box::new(f));
-    }
+        fn send_event(event: $crate::mini_engine::Event) {
+            GAME.with(|game| $crate::mini_engine::dispatch_or_init(&mut *game.borrow_mut(), event))
+        }
+
+        fn main() {
+            $crate::mini_engine::setup_panic_hook();
+        }
+
+        #[no_mangle]
+        extern "C" fn player_joined(player: u32) {
+            send_event($crate::mini_engine::Event::PlayerJoined { player })
+        }
+
+        #[no_mangle]
+        extern "C" fn peer_left(player: u32) {
+            send_event($crate::mini_engine::Event::PlayerLeft { player })
+        }
+
+        #[no_mangle]
+        #[allow(clippy::too_many_arguments)]
+        extern "C" fn pointer_down(
+            player: u32,
+            pointer_id: u32,
+            x: f32,
+            y: f32,
+            kind: u32,
+            button: i32,
+            pressure: f32,
+            tilt_x: f32,
+            tilt_y: f32,
+            width: f32,
+            height: f32,
+        ) {
+            send_event($crate::mini_engine::Event::PointerDown {
+                player,
+                pointer_id,
+                x,
+                y,
+                details: $crate::mini_engine::PointerDetails {
+                    kind: $crate::mini_engine::PointerKind::from_u32(kind),
+                    button: $crate::mini_engine::PointerButton::from_dom(button),
+                    pressure,
+                    tilt_x,
+                    tilt_y,
+                    width,
+                    height,
+                },
+            })
+        }
+
+        #[no_mangle]
+        #[allow(clippy::too_many_arguments)]
+        extern "C" fn pointer_move(
+            player: u32,
+            pointer_id: u32,
+            x: f32,
+            y: f32,
+            kind: u32,
+            button: i32,
+            pressure: f32,
+            tilt_x: f32,
+            tilt_y: f32,
+            width: f32,
+            height: f32,
+        ) {
+            send_event($crate::mini_engine::Event::PointerMove {
+                player,
+                pointer_id,
+                x,
+                y,
+                details: $crate::mini_engine::PointerDetails {
+                    kind: $crate::mini_engine::PointerKind::from_u32(kind),
+                    button: $crate::mini_engine::PointerButton::from_dom(button),
+                    pressure,
+                    tilt_x,
+                    tilt_y,
+                    width,
+                    height,
+                },
+            })
+        }
+
+        #[no_mangle]
+        #[allow(clippy::too_many_arguments)]
+        extern "C" fn pointer_up(
+            player: u32,
+            pointer_id: u32,
+            x: f32,
+            y: f32,
+            kind: u32,
+            button: i32,
+            pressure: f32,
+            tilt_x: f32,
+            tilt_y: f32,
+            width: f32,
+            height: f32,
+        ) {
+            send_event($crate::mini_engine::Event::PointerUp {
+                player,
+                pointer_id,
+                x,
+                y,
+                details: $crate::mini_engine::PointerDetails {
+                    kind: $crate::mini_engine::PointerKind::from_u32(kind),
+                    button: $crate::mini_engine::PointerButton::from_dom(button),
+                    pressure,
+                    tilt_x,
+                    tilt_y,
+                    width,
+                    height,
+                },
+            })
+        }
+
+        #[no_mangle]
+        #[allow(clippy::too_many_arguments)]
+        extern "C" fn pointer_cancel(
+            player: u32,
+            pointer_id: u32,
+            x: f32,
+            y: f32,
+            kind: u32,
+            button: i32,
+            pressure: f32,
+            tilt_x: f32,
+            tilt_y: f32,
+            width: f32,
+            height: f32,
+        ) {
+            send_event($crate::mini_engine::Event::PointerCancel {
+                player,
+                pointer_id,
+                x,
+                y,
+                details: $crate::mini_engine::PointerDetails {
+                    kind: $crate::mini_engine::PointerKind::from_u32(kind),
+                    button: $crate::mini_engine::PointerButton::from_dom(button),
+                    pressure,
+                    tilt_x,
+                    tilt_y,
+                    width,
+                    height,
+                },
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn wheel(
+            player: u32,
+            delta_x: f32,
+            delta_y: f32,
+            delta_mode: u32,
+            x: f32,
+            y: f32,
+        ) {
+            send_event($crate::mini_engine::Event::Wheel {
+                player,
+                delta_x,
+                delta_y,
+                delta_mode: $crate::mini_engine::DeltaMode::from_dom(delta_mode),
+                x,
+                y,
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn key_down(player: u32, key_code: u32) {
+            send_event($crate::mini_engine::Event::KeyDown {
+                player,
+                key: $crate::mini_engine::Key::from_key_code(key_code),
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn key_up(player: u32, key_code: u32) {
+            send_event($crate::mini_engine::Event::KeyUp {
+                player,
+                key: $crate::mini_engine::Key::from_key_code(key_code),
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn image_loaded(image: u32, width: u32, height: u32) {
+            send_event($crate::mini_engine::Event::ImageLoaded {
+                image: $crate::mini_engine::ImageHandle(image),
+                width,
+                height,
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn resize(player: u32, width: f32, height: f32, device_pixel_ratio: f32) {
+            send_event($crate::mini_engine::Event::Resize {
+                player,
+                width,
+                height,
+                device_pixel_ratio,
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn gamepad_connected(player: u32, gamepad: u32) {
+            send_event($crate::mini_engine::Event::GamepadConnected { player, gamepad })
+        }
+
+        #[no_mangle]
+        extern "C" fn gamepad_disconnected(player: u32, gamepad: u32) {
+            send_event($crate::mini_engine::Event::GamepadDisconnected { player, gamepad })
+        }
+
+        #[no_mangle]
+        extern "C" fn gamepad_button(
+            player: u32,
+            gamepad: u32,
+            button: u32,
+            pressed: u32,
+            value: f32,
+        ) {
+            send_event($crate::mini_engine::Event::GamepadButtonChanged {
+                player,
+                gamepad,
+                button: $crate::mini_engine::GamepadButton::from_index(button),
+                pressed: pressed != 0,
+                value,
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn gamepad_axis(player: u32, gamepad: u32, axis: u32, value: f32) {
+            send_event($crate::mini_engine::Event::GamepadAxisChanged {
+                player,
+                gamepad,
+                axis: $crate::mini_engine::GamepadAxis::from_index(axis),
+                value,
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn set_fixed_update_interval(milliseconds: f32) {
+            send_event($crate::mini_engine::Event::SetFixedUpdateInterval {
+                seconds: milliseconds / 1000.0,
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn set_session_seed(seed: u32) {
+            send_event($crate::mini_engine::Event::SetSessionSeed { seed: seed as u64 })
+        }
+
+        /// Passes the hash recorded `ticks_ago` fixed updates ago to the host's `external_state_hash`.
+        /// Call it with `callAndRevert`.
+        #[no_mangle]
+        extern "C" fn state_hash(ticks_ago: u32) {
+            $crate::mini_engine::report_state_hash(ticks_ago)
+        }
+
+        /// Hashes are split in two because the host sends arguments as 64 bit floats.
+        #[no_mangle]
+        extern "C" fn check_state_hash(player: u32, tick: u32, hash_high: u32, hash_low: u32) {
+            send_event($crate::mini_engine::Event::StateHashReported {
+                player,
+                tick: tick as u64,
+                hash: (hash_high as u64) << 32 | hash_low as u64,
+            })
+        }
+
+        #[no_mangle]
+        extern "C" fn fixed_update() {
+            send_event($crate::mini_engine::Event::FixedUpdate)
+        }
+
+        #[no_mangle]
+        extern "C" fn draw(player: u32, alpha: f32) {
+            send_event($crate::mini_engine::Event::Draw { player, alpha })
+        }
+    };
 }
 
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod determinism;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+#[cfg(not(target_arch = "wasm32"))]
+use native as unsafe_external;
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod rasterizer;
+
+#[cfg(target_arch = "wasm32")]
 mod unsafe_external {
     extern "C" {
-        pub(crate) fn set_color(r: u8, g: u8, b: u8, a: f32);
-        pub(crate) fn draw_circle(x: f32, y: f32, r: f32);
-        pub(crate) fn draw_rect(x: f32, y: f32, w: f32, h: f32);
-        pub(crate) fn set_transform(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32);
-        pub(crate) fn begin_path();
-        pub(crate) fn move_to(x: f32, y: f32);
-        pub(crate) fn line_to(x: f32, y: f32);
-        pub(crate) fn fill();
-
+        pub(crate) fn submit(data: *const u8, data_length: u32);
+        pub(crate) fn external_log(data: *const u8, data_length: u32);
+        pub(crate) fn load_image(image: u32, path: *const u8, path_length: u32);
+        pub(crate) fn measure_text(
+            family: *const u8,
+            family_length: u32,
+            size: f32,
+            text: *const u8,
+            text_length: u32,
+        ) -> f32;
+        pub(crate) fn external_state_hash(tick: u32, hash_high: u32, hash_low: u32);
+        pub(crate) fn external_state_hash_mismatch(player: u32, tick: u32);
+        pub(crate) fn external_fixed_update(tick: u32);
     }
 }
 
 pub fn begin_path() {
-    unsafe {
-        unsafe_external::begin_path();
-    }
+    record(DrawCommand::BeginPath);
 }
 
 pub fn fill() {
-    unsafe {
-        unsafe_external::fill();
-    }
+    record(DrawCommand::Fill);
+}
+
+/// Strokes the current path with the current color and line style.
+pub fn stroke() {
+    record(DrawCommand::Stroke);
+}
+
+pub fn set_line_width(width: f32) {
+    record(DrawCommand::SetLineWidth { width });
+}
+
+pub fn set_line_cap(cap: LineCap) {
+    record(DrawCommand::SetLineCap { cap });
+}
+
+pub fn set_line_join(join: LineJoin) {
+    record(DrawCommand::SetLineJoin { join });
+}
+
+/// Sets alternating lengths of dashes and gaps for strokes, starting `offset` into the pattern.
+/// An empty `segments` draws solid lines.
+pub fn set_line_dash(segments: &[f32], offset: f32) {
+    record(DrawCommand::SetLineDash {
+        offset,
+        segments: segments.to_vec(),
+    });
 }
 
 pub fn move_to(x: f32, y: f32) {
-    unsafe {
-        unsafe_external::move_to(x, y);
-    }
+    record(DrawCommand::MoveTo { x, y });
 }
 
 pub fn line_to(x: f32, y: f32) {
-    unsafe {
-        unsafe_external::line_to(x, y);
-    }
+    record(DrawCommand::LineTo { x, y });
 }
 
-pub fn set_transform(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) {
-    unsafe {
-        unsafe_external::set_transform(a, b, c, d, e, f);
-    }
+pub fn quadratic_to(control_x: f32, control_y: f32, x: f32, y: f32) {
+    record(DrawCommand::QuadraticTo {
+        control_x,
+        control_y,
+        x,
+        y,
+    });
 }
 
-/// Resets the transform to the identity transform.
-pub fn reset_transform() {
-    unsafe {
-        unsafe_external::set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
-    }
+pub fn bezier_to(
+    control_0_x: f32,
+    control_0_y: f32,
+    control_1_x: f32,
+    control_1_y: f32,
+    x: f32,
+    y: f32,
+) {
+    record(DrawCommand::BezierTo {
+        control_0_x,
+        control_0_y,
+        control_1_x,
+        control_1_y,
+        x,
+        y,
+    });
+}
+
+/// Adds an arc around (`x`, `y`) to the path, connected to the previous point by a straight line.
+/// Angles are in radians, measured clockwise from the positive x axis.
+pub fn arc(x: f32, y: f32, radius: f32, start_angle: f32, end_angle: f32, counterclockwise: bool) {
+    record(DrawCommand::Arc {
+        x,
+        y,
+        radius,
+        start_angle,
+        end_angle,
+        counterclockwise,
+    });
+}
+
+/// Adds an arc of `radius` that rounds the corner between the current point, (`x1`, `y1`), and (`x2`, `y2`).
+pub fn arc_to(x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+    record(DrawCommand::ArcTo {
+        x1,
+        y1,
+        x2,
+        y2,
+        radius,
+    });
+}
+
+/// Connects the current point back to the start of the subpath.
+pub fn close_path() {
+    record(DrawCommand::ClosePath);
 }
 
 pub fn draw_circle(x: f32, y: f32, radius: f32) {
-    unsafe {
-        unsafe_external::draw_circle(x, y, radius);
-    }
+    record(DrawCommand::DrawCircle { x, y, radius });
 }
 
-pub fn draw_rect(x: f32, y: f32, width: f32, height: f32) {
-    unsafe {
-        unsafe_external::draw_rect(x, y, width, height);
-    }
+/// `rotation` is in radians.
+pub fn draw_ellipse(x: f32, y: f32, radius_x: f32, radius_y: f32, rotation: f32) {
+    record(DrawCommand::DrawEllipse {
+        x,
+        y,
+        radius_x,
+        radius_y,
+        rotation,
+    });
 }
 
-pub fn set_color(r: u8, g: u8, b: u8, a: u8) {
-    unsafe {
-        unsafe_external::set_color(r, g, b, a as f32 / 255.0);
-    }
+pub fn draw_rect(x: f32, y: f32, width: f32, height: f32) {
+    record(DrawCommand::DrawRect {
+        x,
+        y,
+        width,
+        height,
+    });
 }
 
-extern "C" {
-    pub(crate) fn external_log(data: *const u8, data_length: u32);
+/// Sets the color used by both fills and strokes.
+pub fn set_color(r: u8, g: u8, b: u8, a: u8) {
+    record(DrawCommand::SetColor { r, g, b, a });
 }
 
 pub fn log(s: &str) {
     unsafe {
-        external_log(s.as_ptr(), s.len() as _);
+        unsafe_external::external_log(s.as_ptr(), s.len() as _);
     }
 }
 
 /// Sets up a panic hook to print a slightly more useful error-message to the console.
-fn setup_panic_hook() {
+pub fn setup_panic_hook() {
     fn hook_impl(info: &std::panic::PanicInfo) {
         let message = info.to_string();
         log(&message);
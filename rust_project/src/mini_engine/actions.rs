@@ -0,0 +1,380 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{Event, GamepadAxis, GamepadButton, Key, PointerButton};
+
+/// How far a gamepad axis has to be pushed before an action bound to it counts as down.
+pub const AXIS_THRESHOLD: f32 = 0.5;
+
+/// An input an action can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Binding {
+    Key(Key),
+    /// Held while any of the player's pointers holds the button.
+    PointerButton(PointerButton),
+    /// Vertical scrolling in pixels, positive when scrolling down.
+    /// Only contributes to `ActionMap::value`, during the fixed update after the scroll.
+    Wheel,
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis),
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ActionState {
+    down: bool,
+    pressed_update: Option<u64>,
+    released_update: Option<u64>,
+}
+
+struct PlayerInputs<A> {
+    /// Inputs that are held, with their value. Keyed by where the input came from, which is the
+    /// pointer id for pointer buttons, the gamepad index for gamepad inputs, and 0 for keys.
+    held: BTreeMap<(u32, Binding), f32>,
+    /// The fixed update scrolling happened before, and how far it scrolled.
+    wheel: (u64, f32),
+    actions: BTreeMap<A, ActionState>,
+}
+
+impl<A> PlayerInputs<A> {
+    fn new() -> Self {
+        Self {
+            held: BTreeMap::new(),
+            wheel: (0, 0.0),
+            actions: BTreeMap::new(),
+        }
+    }
+}
+
+/// Maps inputs to game defined actions, so the game can ask what a player is doing instead of
+/// which keys and buttons they pressed.
+///
+/// Pass every event to `ActionMap::handle` from `Game::on_event`, and query actions in `Game::fixed_update`,
+/// where "just pressed" means since the previous fixed update. Change bindings only in response to
+/// events so every player's map stays the same.
+pub struct ActionMap<A: Copy + Ord> {
+    bindings: BTreeMap<A, Vec<(Binding, f32)>>,
+    players: BTreeMap<u32, PlayerInputs<A>>,
+    update: u64,
+}
+
+impl<A: Copy + Ord> Default for ActionMap<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Copy + Ord> ActionMap<A> {
+    pub fn new() -> Self {
+        Self {
+            bindings: BTreeMap::new(),
+            players: BTreeMap::new(),
+            update: 0,
+        }
+    }
+
+    pub fn bind(&mut self, action: A, binding: Binding) {
+        self.bind_scaled(action, binding, 1.0);
+    }
+
+    /// Binds an input that contributes its value multiplied by `scale` to `ActionMap::value`.
+    /// A negative scale lets two keys push one action in opposite directions.
+    pub fn bind_scaled(&mut self, action: A, binding: Binding, scale: f32) {
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|(b, _)| *b != binding);
+        bindings.push((binding, scale));
+    }
+
+    pub fn unbind(&mut self, action: A, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|(b, _)| *b != binding);
+        }
+    }
+
+    pub fn clear_bindings(&mut self, action: A) {
+        self.bindings.remove(&action);
+    }
+
+    pub fn bindings(&self, action: A) -> Vec<Binding> {
+        self.bindings
+            .get(&action)
+            .map(|bindings| bindings.iter().map(|(binding, _)| *binding).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_bound(&self, action: A, binding: Binding) -> bool {
+        self.bindings(action).contains(&binding)
+    }
+
+    /// Updates held inputs and action states from an event.
+    pub fn handle(&mut self, event: &Event) {
+        let player = match *event {
+            Event::FixedUpdate => {
+                self.update += 1;
+                return;
+            }
+            Event::PlayerLeft { player } => {
+                self.players.remove(&player);
+                return;
+            }
+            Event::PointerDown {
+                player,
+                pointer_id,
+                details,
+                ..
+            } => {
+                let binding = Binding::PointerButton(details.button);
+                self.inputs(player).held.insert((pointer_id, binding), 1.0);
+                player
+            }
+            Event::PointerUp {
+                player,
+                pointer_id,
+                details,
+                ..
+            } => {
+                let binding = Binding::PointerButton(details.button);
+                self.inputs(player).held.remove(&(pointer_id, binding));
+                player
+            }
+            Event::PointerCancel {
+                player, pointer_id, ..
+            } => {
+                self.inputs(player).held.retain(|(source, binding), _| {
+                    !(*source == pointer_id && matches!(binding, Binding::PointerButton(_)))
+                });
+                player
+            }
+            Event::KeyDown { player, key } => {
+                self.inputs(player).held.insert((0, Binding::Key(key)), 1.0);
+                player
+            }
+            Event::KeyUp { player, key } => {
+                self.inputs(player).held.remove(&(0, Binding::Key(key)));
+                player
+            }
+            Event::Wheel {
+                player,
+                delta_y,
+                delta_mode,
+                ..
+            } => {
+                let update = self.update;
+                let inputs = self.inputs(player);
+                if inputs.wheel.0 != update {
+                    inputs.wheel = (update, 0.0);
+                }
+                inputs.wheel.1 += delta_y * delta_mode.pixel_scale(player);
+                return;
+            }
+            Event::GamepadButtonChanged {
+                player,
+                gamepad,
+                button,
+                pressed,
+                value,
+            } => {
+                let key = (gamepad, Binding::GamepadButton(button));
+                let held = &mut self.inputs(player).held;
+                if pressed {
+                    held.insert(key, value);
+                } else {
+                    held.remove(&key);
+                }
+                player
+            }
+            Event::GamepadAxisChanged {
+                player,
+                gamepad,
+                axis,
+                value,
+            } => {
+                let key = (gamepad, Binding::GamepadAxis(axis));
+                self.inputs(player).held.insert(key, value);
+                player
+            }
+            Event::GamepadDisconnected { player, gamepad } => {
+                self.inputs(player).held.retain(|(source, binding), _| {
+                    !(*source == gamepad
+                        && matches!(binding, Binding::GamepadButton(_) | Binding::GamepadAxis(_)))
+                });
+                player
+            }
+            _ => return,
+        };
+        self.update_actions(player);
+    }
+
+    fn inputs(&mut self, player: u32) -> &mut PlayerInputs<A> {
+        self.players.entry(player).or_insert_with(PlayerInputs::new)
+    }
+
+    /// Returns the value of the input with the largest magnitude among every source of `binding`.
+    fn binding_value(inputs: &PlayerInputs<A>, binding: Binding) -> f32 {
+        inputs
+            .held
+            .iter()
+            .filter(|((_, b), _)| *b == binding)
+            .map(|(_, value)| *value)
+            .fold(0.0, |a: f32, b: f32| if b.abs() > a.abs() { b } else { a })
+    }
+
+    fn binding_down(inputs: &PlayerInputs<A>, binding: Binding) -> bool {
+        match binding {
+            Binding::Wheel => false,
+            Binding::GamepadAxis(_) => Self::binding_value(inputs, binding).abs() > AXIS_THRESHOLD,
+            _ => inputs.held.keys().any(|(_, b)| *b == binding),
+        }
+    }
+
+    fn update_actions(&mut self, player: u32) {
+        let Some(inputs) = self.players.get_mut(&player) else {
+            return;
+        };
+        // Actions that lost their bindings are included so they are released.
+        let actions: BTreeSet<A> = (self.bindings.keys())
+            .chain(inputs.actions.keys())
+            .copied()
+            .collect();
+        for action in actions {
+            let down = self.bindings.get(&action).is_some_and(|bindings| {
+                bindings
+                    .iter()
+                    .any(|(binding, _)| Self::binding_down(inputs, *binding))
+            });
+            let state = inputs.actions.entry(action).or_default();
+            if down && !state.down {
+                state.pressed_update = Some(self.update);
+            } else if !down && state.down {
+                state.released_update = Some(self.update);
+            }
+            state.down = down;
+        }
+    }
+
+    fn state(&self, player: u32, action: A) -> ActionState {
+        self.players
+            .get(&player)
+            .and_then(|inputs| inputs.actions.get(&action).copied())
+            .unwrap_or_default()
+    }
+
+    pub fn is_down(&self, player: u32, action: A) -> bool {
+        self.state(player, action).down
+    }
+
+    /// True if the action went down since the previous fixed update, even if it has been released since.
+    pub fn is_just_pressed(&self, player: u32, action: A) -> bool {
+        let pressed = self.state(player, action).pressed_update;
+        pressed.is_some_and(|update| update + 1 == self.update)
+    }
+
+    /// True if the action was released since the previous fixed update, even if it has been pressed since.
+    pub fn is_just_released(&self, player: u32, action: A) -> bool {
+        let released = self.state(player, action).released_update;
+        released.is_some_and(|update| update + 1 == self.update)
+    }
+
+    /// The sum of every bound input's value times its scale.
+    ///
+    /// Held keys and buttons have a value of 1, or how far they are pressed for analog buttons.
+    /// Gamepad axes have their position, and the wheel has how far it scrolled since the previous fixed update.
+    pub fn value(&self, player: u32, action: A) -> f32 {
+        let (Some(inputs), Some(bindings)) =
+            (self.players.get(&player), self.bindings.get(&action))
+        else {
+            return 0.0;
+        };
+        bindings
+            .iter()
+            .map(|(binding, scale)| {
+                let value = match binding {
+                    Binding::Wheel if inputs.wheel.0 + 1 == self.update => inputs.wheel.1,
+                    Binding::Wheel => 0.0,
+                    _ => Self::binding_value(inputs, *binding),
+                };
+                value * scale
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_engine::DeltaMode;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    enum Action {
+        Jump,
+        Turn,
+    }
+
+    #[test]
+    fn actions_follow_bound_inputs() {
+        let mut actions = ActionMap::new();
+        actions.bind(Action::Jump, Binding::Key(Key::Space));
+        actions.bind(Action::Jump, Binding::GamepadButton(GamepadButton::South));
+        actions.bind_scaled(Action::Turn, Binding::Key(Key::Q), -1.0);
+        actions.bind_scaled(Action::Turn, Binding::Key(Key::E), 1.0);
+        actions.bind_scaled(Action::Turn, Binding::Wheel, 0.01);
+
+        let player = 3;
+        actions.handle(&Event::KeyDown {
+            player,
+            key: Key::Space,
+        });
+        actions.handle(&Event::KeyDown {
+            player,
+            key: Key::Q,
+        });
+        actions.handle(&Event::FixedUpdate);
+        assert!(actions.is_down(player, Action::Jump));
+        assert!(actions.is_just_pressed(player, Action::Jump));
+        assert_eq!(actions.value(player, Action::Turn), -1.0);
+
+        // Holding the gamepad button as well keeps the action down after the key is released.
+        actions.handle(&Event::GamepadConnected { player, gamepad: 0 });
+        actions.handle(&Event::GamepadButtonChanged {
+            player,
+            gamepad: 0,
+            button: GamepadButton::South,
+            pressed: true,
+            value: 1.0,
+        });
+        actions.handle(&Event::KeyUp {
+            player,
+            key: Key::Space,
+        });
+        actions.handle(&Event::Wheel {
+            player,
+            delta_x: 0.0,
+            delta_y: 100.0,
+            delta_mode: DeltaMode::Pixel,
+            x: 0.0,
+            y: 0.0,
+        });
+        actions.handle(&Event::FixedUpdate);
+        assert!(actions.is_down(player, Action::Jump));
+        assert!(!actions.is_just_pressed(player, Action::Jump));
+        assert_eq!(actions.value(player, Action::Turn), 0.0);
+
+        actions.handle(&Event::GamepadDisconnected { player, gamepad: 0 });
+        actions.handle(&Event::FixedUpdate);
+        assert!(actions.is_just_released(player, Action::Jump));
+        assert_eq!(actions.value(player, Action::Turn), -1.0);
+
+        // Rebinding takes effect for the next input.
+        actions.unbind(Action::Turn, Binding::Key(Key::Q));
+        actions.bind(Action::Jump, Binding::Key(Key::Q));
+        actions.handle(&Event::KeyUp {
+            player,
+            key: Key::Q,
+        });
+        actions.handle(&Event::KeyDown {
+            player,
+            key: Key::Q,
+        });
+        actions.handle(&Event::FixedUpdate);
+        assert!(actions.is_just_pressed(player, Action::Jump));
+        assert_eq!(actions.value(player, Action::Turn), 0.0);
+    }
+}
@@ -0,0 +1,140 @@
+use super::transform::{transform, Transform};
+
+/// The size of the canvas in CSS pixels, and how many physical pixels each CSS pixel covers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub width: f32,
+    pub height: f32,
+    pub device_pixel_ratio: f32,
+}
+
+impl Viewport {
+    pub const fn new(width: f32, height: f32, device_pixel_ratio: f32) -> Self {
+        Self {
+            width,
+            height,
+            device_pixel_ratio,
+        }
+    }
+}
+
+/// How a `Camera` picks its scale from the viewport size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fit {
+    /// A fixed number of CSS pixels per world unit, whatever the viewport size.
+    PixelsPerUnit(f32),
+    /// Shows the whole `width` by `height` area, leaving extra space on the long side.
+    Contain { width: f32, height: f32 },
+    /// Fills the viewport with the `width` by `height` area, cropping the long side.
+    Cover { width: f32, height: f32 },
+}
+
+/// Maps between world coordinates and screen coordinates.
+///
+/// Screen coordinates are CSS pixels from the top left of the canvas, which is what pointer events use.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// The world position shown at the center of the viewport.
+    pub center: [f32; 2],
+    /// Multiplies the scale picked by `fit`. Larger values zoom in.
+    pub zoom: f32,
+    pub fit: Fit,
+    pub viewport: Viewport,
+}
+
+impl Camera {
+    pub const fn new(center: [f32; 2], fit: Fit, viewport: Viewport) -> Self {
+        Self {
+            center,
+            zoom: 1.0,
+            fit,
+            viewport,
+        }
+    }
+
+    /// How many CSS pixels one world unit covers.
+    pub fn pixels_per_unit(&self) -> f32 {
+        let Viewport { width, height, .. } = self.viewport;
+        let scale = match self.fit {
+            Fit::PixelsPerUnit(pixels) => pixels,
+            Fit::Contain {
+                width: world_width,
+                height: world_height,
+            } => (width / world_width).min(height / world_height),
+            Fit::Cover {
+                width: world_width,
+                height: world_height,
+            } => (width / world_width).max(height / world_height),
+        };
+        scale * self.zoom
+    }
+
+    pub fn world_to_screen(&self, x: f32, y: f32) -> [f32; 2] {
+        let scale = self.pixels_per_unit();
+        [
+            (x - self.center[0]) * scale + self.viewport.width / 2.0,
+            (y - self.center[1]) * scale + self.viewport.height / 2.0,
+        ]
+    }
+
+    pub fn screen_to_world(&self, x: f32, y: f32) -> [f32; 2] {
+        let scale = self.pixels_per_unit();
+        [
+            (x - self.viewport.width / 2.0) / scale + self.center[0],
+            (y - self.viewport.height / 2.0) / scale + self.center[1],
+        ]
+    }
+
+    /// The transform from world coordinates to canvas pixels, which accounts for the device pixel ratio.
+    pub fn transform(&self) -> Transform {
+        let ratio = self.viewport.device_pixel_ratio;
+        let scale = self.pixels_per_unit() * ratio;
+        let [x, y] = self.world_to_screen(0.0, 0.0);
+        Transform::new(scale, 0.0, 0.0, scale, x * ratio, y * ratio)
+    }
+
+    /// Multiplies the current transform by `Camera::transform` so what is drawn next is in world coordinates.
+    pub fn apply(&self) {
+        let Transform { a, b, c, d, e, f } = self.transform();
+        transform(a, b, c, d, e, f);
+    }
+
+    /// Moves the camera by a distance in screen pixels, so content follows a dragged pointer.
+    pub fn pan_screen(&mut self, delta_x: f32, delta_y: f32) {
+        let scale = self.pixels_per_unit();
+        self.center[0] -= delta_x / scale;
+        self.center[1] -= delta_y / scale;
+    }
+
+    /// Multiplies `zoom` by `factor` while keeping the world position under the screen position in place.
+    pub fn zoom_at(&mut self, screen_x: f32, screen_y: f32, factor: f32) {
+        let before = self.screen_to_world(screen_x, screen_y);
+        self.zoom *= factor;
+        let after = self.screen_to_world(screen_x, screen_y);
+        self.center[0] += before[0] - after[0];
+        self.center[1] += before[1] - after[1];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_keeps_point_under_cursor() {
+        let fit = Fit::Contain {
+            width: 2.0,
+            height: 1.0,
+        };
+        let mut camera = Camera::new([1.0, 0.5], fit, Viewport::new(400.0, 100.0, 2.0));
+        // The height limits the scale, so the world is letterboxed horizontally.
+        assert_eq!(camera.pixels_per_unit(), 100.0);
+        assert_eq!(camera.world_to_screen(0.0, 0.0), [100.0, 0.0]);
+        assert_eq!(camera.transform().apply(0.0, 0.0), [200.0, 0.0]);
+
+        let world = camera.screen_to_world(300.0, 25.0);
+        camera.zoom_at(300.0, 25.0, 2.0);
+        assert_eq!(camera.pixels_per_unit(), 200.0);
+        assert_eq!(camera.world_to_screen(world[0], world[1]), [300.0, 25.0]);
+    }
+}
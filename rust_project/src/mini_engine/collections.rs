@@ -0,0 +1,31 @@
+//! Collections that iterate in the same order on every peer.
+//!
+//! `HashMap` and `HashSet` iterate in an order that depends on a random seed, so code that
+//! iterates them to change the simulation can give each peer a different result.
+//! These iterate in key order, so the order only depends on what is in them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A map for game state, which iterates in key order.
+pub type DeterministicMap<K, V> = BTreeMap<K, V>;
+
+/// A set for game state, which iterates in order.
+pub type DeterministicSet<T> = BTreeSet<T>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iteration_is_in_key_order() {
+        let mut map = DeterministicMap::new();
+        for key in [(3, 0), (1, 2), (2, 1), (1, 0)] {
+            map.insert(key, ());
+        }
+        let keys: Vec<_> = map.keys().copied().collect();
+        assert_eq!(keys, [(1, 0), (1, 2), (2, 1), (3, 0)]);
+
+        let set: DeterministicSet<u32> = [9, 4, 7].into_iter().collect();
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), [4, 7, 9]);
+    }
+}
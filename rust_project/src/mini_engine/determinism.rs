@@ -0,0 +1,240 @@
+//! Checks that a game reaches the same state no matter when each event arrives, like Tangle's peers must.
+//!
+//! `mini_engine` keeps its state per thread, so each copy of the game runs on its own thread.
+//! That also gives each copy its own `HashMap` ordering, so iterating one where order matters shows up as a mismatch.
+
+use std::marker::PhantomData;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+use super::native::Harness;
+use super::{Event, Game};
+
+/// An event, the fixed update it happens before, and how many fixed updates late the
+/// second copy of the game hears about it.
+#[derive(Clone, Copy, Debug)]
+pub struct ScheduledEvent {
+    pub tick: u64,
+    pub delay: u64,
+    pub event: Event,
+}
+
+enum Command {
+    Event(Event),
+    FixedUpdate(Sender<u64>),
+}
+
+/// A copy of the game running on its own thread.
+struct Replica {
+    commands: Sender<Command>,
+}
+
+impl Replica {
+    fn spawn<G: Game>() -> Self {
+        let (commands, receiver) = channel();
+        thread::spawn(move || {
+            let mut harness = Harness::<G>::new();
+            for command in receiver {
+                match command {
+                    Command::Event(event) => harness.send(event),
+                    Command::FixedUpdate(reply) => {
+                        harness.fixed_updates(1);
+                        let _ = reply.send(harness.state_hash());
+                    }
+                }
+            }
+        });
+        Self { commands }
+    }
+
+    fn send(&self, event: Event) {
+        self.commands
+            .send(Command::Event(event))
+            .expect("A copy of the game panicked");
+    }
+
+    /// Runs a fixed update and returns the hash of the state after it.
+    fn fixed_update(&self) -> u64 {
+        let (reply, hash) = channel();
+        self.commands
+            .send(Command::FixedUpdate(reply))
+            .expect("A copy of the game panicked");
+        hash.recv().expect("A copy of the game panicked")
+    }
+}
+
+/// Runs two copies of a game from the same events and panics if their `state_hash`es ever disagree.
+///
+/// The first copy gets every event on time. The second gets each event `ScheduledEvent::delay`
+/// fixed updates late, and when a late event arrives it rolls back the way Tangle does:
+/// it starts over on a fresh thread and replays every event it knows about.
+pub struct DeterminismHarness<G: Game> {
+    events: Vec<ScheduledEvent>,
+    game: PhantomData<fn() -> G>,
+}
+
+impl<G: Game> Default for DeterminismHarness<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G: Game> DeterminismHarness<G> {
+    pub fn new() -> Self {
+        Self {
+            events: Vec::new(),
+            game: PhantomData,
+        }
+    }
+
+    /// Sends `event` before fixed update `tick`, on time to both copies.
+    pub fn send(&mut self, tick: u64, event: Event) {
+        self.send_late(tick, 0, event);
+    }
+
+    /// Sends `event` before fixed update `tick`, but the second copy only hears about it
+    /// `delay` fixed updates later. Events for the same tick are processed in the order they were sent.
+    pub fn send_late(&mut self, tick: u64, delay: u64, event: Event) {
+        self.events.push(ScheduledEvent { tick, delay, event });
+    }
+
+    /// Runs `ticks` fixed updates on both copies and returns the hash after each one.
+    ///
+    /// Panics at the first fixed update where the copies disagree, once the second copy
+    /// has heard about every event up to it.
+    pub fn run(&self, ticks: u64) -> Vec<u64> {
+        for scheduled in &self.events {
+            assert!(
+                scheduled.tick + scheduled.delay < ticks,
+                "{:?} arrives after the last fixed update",
+                scheduled
+            );
+        }
+
+        let on_time = Replica::spawn::<G>();
+        let mut expected = Vec::new();
+        for tick in 0..ticks {
+            for scheduled in self.events.iter().filter(|e| e.tick == tick) {
+                on_time.send(scheduled.event);
+            }
+            expected.push(on_time.fixed_update());
+        }
+
+        let arrived =
+            |scheduled: &ScheduledEvent, now: u64| scheduled.tick + scheduled.delay <= now;
+        let check = |tick: u64, hash: u64, now: u64| {
+            let complete = (self.events.iter())
+                .filter(|e| e.tick <= tick)
+                .all(|e| arrived(e, now));
+            assert!(
+                !complete || hash == expected[tick as usize],
+                "The copies disagree after fixed update {} (checked at fixed update {}): {:016x} != {:016x}",
+                tick,
+                now,
+                expected[tick as usize],
+                hash
+            );
+        };
+
+        let mut late = Replica::spawn::<G>();
+        for now in 0..ticks {
+            let rollback = (self.events.iter()).any(|e| e.delay > 0 && e.tick + e.delay == now);
+            if rollback {
+                late = Replica::spawn::<G>();
+                for tick in 0..now {
+                    for scheduled in self.events.iter().filter(|e| e.tick == tick) {
+                        if arrived(scheduled, now) {
+                            late.send(scheduled.event);
+                        }
+                    }
+                    check(tick, late.fixed_update(), now);
+                }
+            }
+            for scheduled in self.events.iter().filter(|e| e.tick == now) {
+                if arrived(scheduled, now) {
+                    late.send(scheduled.event);
+                }
+            }
+            check(now, late.fixed_update(), now);
+        }
+        expected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_engine::StateHasher;
+    use std::collections::{BTreeSet, HashSet};
+    use std::hash::Hash;
+
+    /// Depends on exactly when each player joined.
+    struct Joins {
+        players: BTreeSet<u32>,
+        history: u64,
+    }
+
+    impl Game for Joins {
+        fn init() -> Self {
+            Self {
+                players: BTreeSet::new(),
+                history: 0,
+            }
+        }
+
+        fn fixed_update(&mut self) {
+            self.history = self.history * 31 + self.players.len() as u64;
+        }
+
+        fn on_player_joined(&mut self, player: u32) {
+            self.players.insert(player);
+        }
+
+        fn hash_state(&self, hasher: &mut StateHasher) {
+            self.history.hash(hasher);
+        }
+    }
+
+    /// Depends on the order a `HashSet` iterates in.
+    struct HashOrder {
+        players: HashSet<u32>,
+    }
+
+    impl Game for HashOrder {
+        fn init() -> Self {
+            Self {
+                players: HashSet::new(),
+            }
+        }
+
+        fn on_player_joined(&mut self, player: u32) {
+            self.players.insert(player);
+        }
+
+        fn hash_state(&self, hasher: &mut StateHasher) {
+            for player in &self.players {
+                player.hash(hasher);
+            }
+        }
+    }
+
+    #[test]
+    fn late_events_are_rolled_back() {
+        let mut harness = DeterminismHarness::<Joins>::new();
+        harness.send(0, Event::PlayerJoined { player: 1 });
+        harness.send_late(3, 4, Event::PlayerJoined { player: 2 });
+        harness.send_late(5, 1, Event::PlayerJoined { player: 3 });
+        let hashes = harness.run(12);
+        assert_eq!(hashes.len(), 12);
+    }
+
+    #[test]
+    #[should_panic(expected = "The copies disagree")]
+    fn hash_order_is_caught() {
+        let mut harness = DeterminismHarness::<HashOrder>::new();
+        for player in 0..32 {
+            harness.send(0, Event::PlayerJoined { player });
+        }
+        harness.run(1);
+    }
+}
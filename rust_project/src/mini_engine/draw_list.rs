@@ -0,0 +1,779 @@
+//! Drawing is recorded into a `DrawList` and handed to the host with a single `submit` call
+//! per frame, instead of calling into the host once per primitive.
+//!
+//! # Format
+//!
+//! A draw list is a sequence of commands packed with no padding. Each command is a one byte
+//! opcode followed by its arguments. `f32` and `u32` arguments are little-endian.
+//! A `[f32]` argument is a `u32` count followed by that many `f32`s.
+//! A `str` argument is a `u32` byte length followed by that many bytes of UTF-8.
+//!
+//! | Opcode | Command         | Arguments                                      |
+//! |--------|-----------------|------------------------------------------------|
+//! | 0      | `SetColor`      | `r: u8, g: u8, b: u8, a: u8`                   |
+//! | 1      | `DrawCircle`    | `x: f32, y: f32, radius: f32`                  |
+//! | 2      | `DrawRect`      | `x: f32, y: f32, width: f32, height: f32`      |
+//! | 3      | `SetTransform`  | `a: f32, b: f32, c: f32, d: f32, e: f32, f: f32` |
+//! | 4      | `BeginPath`     |                                                |
+//! | 5      | `MoveTo`        | `x: f32, y: f32`                               |
+//! | 6      | `LineTo`        | `x: f32, y: f32`                               |
+//! | 7      | `Fill`          |                                                |
+//! | 8      | `Stroke`        |                                                |
+//! | 9      | `SetLineWidth`  | `width: f32`                                   |
+//! | 10     | `SetLineCap`    | `cap: u8` (0: butt, 1: round, 2: square)       |
+//! | 11     | `SetLineJoin`   | `join: u8` (0: miter, 1: round, 2: bevel)      |
+//! | 12     | `SetLineDash`   | `offset: f32, segments: [f32]`                 |
+//! | 13     | `QuadraticTo`   | `control_x: f32, control_y: f32, x: f32, y: f32` |
+//! | 14     | `BezierTo`      | `control_0_x: f32, control_0_y: f32, control_1_x: f32, control_1_y: f32, x: f32, y: f32` |
+//! | 15     | `Arc`           | `x: f32, y: f32, radius: f32, start_angle: f32, end_angle: f32, counterclockwise: u8` |
+//! | 16     | `ArcTo`         | `x1: f32, y1: f32, x2: f32, y2: f32, radius: f32` |
+//! | 17     | `ClosePath`     |                                                |
+//! | 18     | `DrawEllipse`   | `x: f32, y: f32, radius_x: f32, radius_y: f32, rotation: f32` |
+//! | 19     | `SetFont`       | `size: f32, family: str`                       |
+//! | 20     | `SetTextAlign`  | `align: u8` (0: left, 1: center, 2: right)     |
+//! | 21     | `SetTextBaseline` | `baseline: u8` (0: alphabetic, 1: top, 2: middle, 3: bottom) |
+//! | 22     | `DrawText`      | `x: f32, y: f32, text: str`                    |
+//! | 23     | `DrawImage`     | `image: u32, source_x: f32, source_y: f32, source_width: f32, source_height: f32, x: f32, y: f32, width: f32, height: f32` |
+//!
+//! The commands behave like their HTML canvas counterparts. `DrawCircle`, `DrawRect`, and `DrawEllipse`
+//! replace the current path with the shape and fill it. `SetColor` applies to both fills and strokes.
+//! `DrawImage` ignores the current color and does nothing if the image has not loaded.
+//! `DrawText` fills the text with the current color. The transform, font, text align, and
+//! text baseline are reset to their defaults at the start of every draw list.
+
+use std::cell::RefCell;
+
+use super::image::{ImageHandle, Rect};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// Where text is drawn relative to the x coordinate passed to `draw_text`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Where text is drawn relative to the y coordinate passed to `draw_text`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextBaseline {
+    #[default]
+    Alphabetic,
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// A single drawing command. See the module docs for how each is encoded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    SetColor {
+        r: u8,
+        g: u8,
+        b: u8,
+        a: u8,
+    },
+    DrawCircle {
+        x: f32,
+        y: f32,
+        radius: f32,
+    },
+    DrawRect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    SetTransform {
+        a: f32,
+        b: f32,
+        c: f32,
+        d: f32,
+        e: f32,
+        f: f32,
+    },
+    BeginPath,
+    MoveTo {
+        x: f32,
+        y: f32,
+    },
+    LineTo {
+        x: f32,
+        y: f32,
+    },
+    Fill,
+    Stroke,
+    SetLineWidth {
+        width: f32,
+    },
+    SetLineCap {
+        cap: LineCap,
+    },
+    SetLineJoin {
+        join: LineJoin,
+    },
+    /// An empty `segments` draws solid lines.
+    SetLineDash {
+        offset: f32,
+        segments: Vec<f32>,
+    },
+    QuadraticTo {
+        control_x: f32,
+        control_y: f32,
+        x: f32,
+        y: f32,
+    },
+    BezierTo {
+        control_0_x: f32,
+        control_0_y: f32,
+        control_1_x: f32,
+        control_1_y: f32,
+        x: f32,
+        y: f32,
+    },
+    /// Angles are in radians, measured clockwise from the positive x axis.
+    Arc {
+        x: f32,
+        y: f32,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        counterclockwise: bool,
+    },
+    ArcTo {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        radius: f32,
+    },
+    ClosePath,
+    DrawEllipse {
+        x: f32,
+        y: f32,
+        radius_x: f32,
+        radius_y: f32,
+        rotation: f32,
+    },
+    /// `size` is in pixels before the transform is applied.
+    SetFont {
+        family: String,
+        size: f32,
+    },
+    SetTextAlign {
+        align: TextAlign,
+    },
+    SetTextBaseline {
+        baseline: TextBaseline,
+    },
+    DrawText {
+        x: f32,
+        y: f32,
+        text: String,
+    },
+    DrawImage {
+        image: ImageHandle,
+        source: Rect,
+        destination: Rect,
+    },
+}
+
+mod opcode {
+    pub const SET_COLOR: u8 = 0;
+    pub const DRAW_CIRCLE: u8 = 1;
+    pub const DRAW_RECT: u8 = 2;
+    pub const SET_TRANSFORM: u8 = 3;
+    pub const BEGIN_PATH: u8 = 4;
+    pub const MOVE_TO: u8 = 5;
+    pub const LINE_TO: u8 = 6;
+    pub const FILL: u8 = 7;
+    pub const STROKE: u8 = 8;
+    pub const SET_LINE_WIDTH: u8 = 9;
+    pub const SET_LINE_CAP: u8 = 10;
+    pub const SET_LINE_JOIN: u8 = 11;
+    pub const SET_LINE_DASH: u8 = 12;
+    pub const QUADRATIC_TO: u8 = 13;
+    pub const BEZIER_TO: u8 = 14;
+    pub const ARC: u8 = 15;
+    pub const ARC_TO: u8 = 16;
+    pub const CLOSE_PATH: u8 = 17;
+    pub const DRAW_ELLIPSE: u8 = 18;
+    pub const SET_FONT: u8 = 19;
+    pub const SET_TEXT_ALIGN: u8 = 20;
+    pub const SET_TEXT_BASELINE: u8 = 21;
+    pub const DRAW_TEXT: u8 = 22;
+    pub const DRAW_IMAGE: u8 = 23;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    UnknownOpcode { opcode: u8, offset: usize },
+    UnexpectedEnd { offset: usize },
+    InvalidValue { offset: usize },
+}
+
+/// Commands encoded in the binary format described in the module docs.
+#[derive(Clone, Debug, Default)]
+pub struct DrawList {
+    bytes: Vec<u8>,
+}
+
+impl DrawList {
+    pub const fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    pub fn push(&mut self, command: DrawCommand) {
+        let bytes = &mut self.bytes;
+        match command {
+            DrawCommand::SetColor { r, g, b, a } => {
+                bytes.extend_from_slice(&[opcode::SET_COLOR, r, g, b, a]);
+            }
+            DrawCommand::DrawCircle { x, y, radius } => {
+                bytes.push(opcode::DRAW_CIRCLE);
+                push_f32s(bytes, &[x, y, radius]);
+            }
+            DrawCommand::DrawRect {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                bytes.push(opcode::DRAW_RECT);
+                push_f32s(bytes, &[x, y, width, height]);
+            }
+            DrawCommand::SetTransform { a, b, c, d, e, f } => {
+                bytes.push(opcode::SET_TRANSFORM);
+                push_f32s(bytes, &[a, b, c, d, e, f]);
+            }
+            DrawCommand::BeginPath => bytes.push(opcode::BEGIN_PATH),
+            DrawCommand::MoveTo { x, y } => {
+                bytes.push(opcode::MOVE_TO);
+                push_f32s(bytes, &[x, y]);
+            }
+            DrawCommand::LineTo { x, y } => {
+                bytes.push(opcode::LINE_TO);
+                push_f32s(bytes, &[x, y]);
+            }
+            DrawCommand::Fill => bytes.push(opcode::FILL),
+            DrawCommand::Stroke => bytes.push(opcode::STROKE),
+            DrawCommand::SetLineWidth { width } => {
+                bytes.push(opcode::SET_LINE_WIDTH);
+                push_f32s(bytes, &[width]);
+            }
+            DrawCommand::SetLineCap { cap } => {
+                let cap = match cap {
+                    LineCap::Butt => 0,
+                    LineCap::Round => 1,
+                    LineCap::Square => 2,
+                };
+                bytes.extend_from_slice(&[opcode::SET_LINE_CAP, cap]);
+            }
+            DrawCommand::SetLineJoin { join } => {
+                let join = match join {
+                    LineJoin::Miter => 0,
+                    LineJoin::Round => 1,
+                    LineJoin::Bevel => 2,
+                };
+                bytes.extend_from_slice(&[opcode::SET_LINE_JOIN, join]);
+            }
+            DrawCommand::SetLineDash { offset, segments } => {
+                bytes.push(opcode::SET_LINE_DASH);
+                push_f32s(bytes, &[offset]);
+                push_f32_slice(bytes, &segments);
+            }
+            DrawCommand::QuadraticTo {
+                control_x,
+                control_y,
+                x,
+                y,
+            } => {
+                bytes.push(opcode::QUADRATIC_TO);
+                push_f32s(bytes, &[control_x, control_y, x, y]);
+            }
+            DrawCommand::BezierTo {
+                control_0_x,
+                control_0_y,
+                control_1_x,
+                control_1_y,
+                x,
+                y,
+            } => {
+                bytes.push(opcode::BEZIER_TO);
+                push_f32s(
+                    bytes,
+                    &[control_0_x, control_0_y, control_1_x, control_1_y, x, y],
+                );
+            }
+            DrawCommand::Arc {
+                x,
+                y,
+                radius,
+                start_angle,
+                end_angle,
+                counterclockwise,
+            } => {
+                bytes.push(opcode::ARC);
+                push_f32s(bytes, &[x, y, radius, start_angle, end_angle]);
+                bytes.push(counterclockwise as u8);
+            }
+            DrawCommand::ArcTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                radius,
+            } => {
+                bytes.push(opcode::ARC_TO);
+                push_f32s(bytes, &[x1, y1, x2, y2, radius]);
+            }
+            DrawCommand::ClosePath => bytes.push(opcode::CLOSE_PATH),
+            DrawCommand::DrawEllipse {
+                x,
+                y,
+                radius_x,
+                radius_y,
+                rotation,
+            } => {
+                bytes.push(opcode::DRAW_ELLIPSE);
+                push_f32s(bytes, &[x, y, radius_x, radius_y, rotation]);
+            }
+            DrawCommand::SetFont { family, size } => {
+                bytes.push(opcode::SET_FONT);
+                push_f32s(bytes, &[size]);
+                push_str(bytes, &family);
+            }
+            DrawCommand::SetTextAlign { align } => {
+                let align = match align {
+                    TextAlign::Left => 0,
+                    TextAlign::Center => 1,
+                    TextAlign::Right => 2,
+                };
+                bytes.extend_from_slice(&[opcode::SET_TEXT_ALIGN, align]);
+            }
+            DrawCommand::SetTextBaseline { baseline } => {
+                let baseline = match baseline {
+                    TextBaseline::Alphabetic => 0,
+                    TextBaseline::Top => 1,
+                    TextBaseline::Middle => 2,
+                    TextBaseline::Bottom => 3,
+                };
+                bytes.extend_from_slice(&[opcode::SET_TEXT_BASELINE, baseline]);
+            }
+            DrawCommand::DrawText { x, y, text } => {
+                bytes.push(opcode::DRAW_TEXT);
+                push_f32s(bytes, &[x, y]);
+                push_str(bytes, &text);
+            }
+            DrawCommand::DrawImage {
+                image,
+                source,
+                destination,
+            } => {
+                bytes.push(opcode::DRAW_IMAGE);
+                bytes.extend_from_slice(&image.0.to_le_bytes());
+                push_f32s(
+                    bytes,
+                    &[
+                        source.x,
+                        source.y,
+                        source.width,
+                        source.height,
+                        destination.x,
+                        destination.y,
+                        destination.width,
+                        destination.height,
+                    ],
+                );
+            }
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Removes all commands but keeps the allocation around for the next frame.
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Vec<DrawCommand>, DecodeError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        let mut commands = Vec::new();
+        while reader.offset < bytes.len() {
+            let offset = reader.offset;
+            let command = match reader.u8()? {
+                opcode::SET_COLOR => DrawCommand::SetColor {
+                    r: reader.u8()?,
+                    g: reader.u8()?,
+                    b: reader.u8()?,
+                    a: reader.u8()?,
+                },
+                opcode::DRAW_CIRCLE => DrawCommand::DrawCircle {
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                    radius: reader.f32()?,
+                },
+                opcode::DRAW_RECT => DrawCommand::DrawRect {
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                    width: reader.f32()?,
+                    height: reader.f32()?,
+                },
+                opcode::SET_TRANSFORM => DrawCommand::SetTransform {
+                    a: reader.f32()?,
+                    b: reader.f32()?,
+                    c: reader.f32()?,
+                    d: reader.f32()?,
+                    e: reader.f32()?,
+                    f: reader.f32()?,
+                },
+                opcode::BEGIN_PATH => DrawCommand::BeginPath,
+                opcode::MOVE_TO => DrawCommand::MoveTo {
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                },
+                opcode::LINE_TO => DrawCommand::LineTo {
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                },
+                opcode::FILL => DrawCommand::Fill,
+                opcode::STROKE => DrawCommand::Stroke,
+                opcode::SET_LINE_WIDTH => DrawCommand::SetLineWidth {
+                    width: reader.f32()?,
+                },
+                opcode::SET_LINE_CAP => DrawCommand::SetLineCap {
+                    cap: match reader.u8()? {
+                        0 => LineCap::Butt,
+                        1 => LineCap::Round,
+                        2 => LineCap::Square,
+                        _ => return Err(DecodeError::InvalidValue { offset }),
+                    },
+                },
+                opcode::SET_LINE_JOIN => DrawCommand::SetLineJoin {
+                    join: match reader.u8()? {
+                        0 => LineJoin::Miter,
+                        1 => LineJoin::Round,
+                        2 => LineJoin::Bevel,
+                        _ => return Err(DecodeError::InvalidValue { offset }),
+                    },
+                },
+                opcode::SET_LINE_DASH => DrawCommand::SetLineDash {
+                    offset: reader.f32()?,
+                    segments: reader.f32_slice()?,
+                },
+                opcode::QUADRATIC_TO => DrawCommand::QuadraticTo {
+                    control_x: reader.f32()?,
+                    control_y: reader.f32()?,
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                },
+                opcode::BEZIER_TO => DrawCommand::BezierTo {
+                    control_0_x: reader.f32()?,
+                    control_0_y: reader.f32()?,
+                    control_1_x: reader.f32()?,
+                    control_1_y: reader.f32()?,
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                },
+                opcode::ARC => DrawCommand::Arc {
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                    radius: reader.f32()?,
+                    start_angle: reader.f32()?,
+                    end_angle: reader.f32()?,
+                    counterclockwise: reader.u8()? != 0,
+                },
+                opcode::ARC_TO => DrawCommand::ArcTo {
+                    x1: reader.f32()?,
+                    y1: reader.f32()?,
+                    x2: reader.f32()?,
+                    y2: reader.f32()?,
+                    radius: reader.f32()?,
+                },
+                opcode::CLOSE_PATH => DrawCommand::ClosePath,
+                opcode::DRAW_ELLIPSE => DrawCommand::DrawEllipse {
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                    radius_x: reader.f32()?,
+                    radius_y: reader.f32()?,
+                    rotation: reader.f32()?,
+                },
+                opcode::SET_FONT => {
+                    // Read in encoding order, which differs from field order.
+                    let size = reader.f32()?;
+                    DrawCommand::SetFont {
+                        family: reader.str()?,
+                        size,
+                    }
+                }
+                opcode::SET_TEXT_ALIGN => DrawCommand::SetTextAlign {
+                    align: match reader.u8()? {
+                        0 => TextAlign::Left,
+                        1 => TextAlign::Center,
+                        2 => TextAlign::Right,
+                        _ => return Err(DecodeError::InvalidValue { offset }),
+                    },
+                },
+                opcode::SET_TEXT_BASELINE => DrawCommand::SetTextBaseline {
+                    baseline: match reader.u8()? {
+                        0 => TextBaseline::Alphabetic,
+                        1 => TextBaseline::Top,
+                        2 => TextBaseline::Middle,
+                        3 => TextBaseline::Bottom,
+                        _ => return Err(DecodeError::InvalidValue { offset }),
+                    },
+                },
+                opcode::DRAW_TEXT => DrawCommand::DrawText {
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                    text: reader.str()?,
+                },
+                opcode::DRAW_IMAGE => DrawCommand::DrawImage {
+                    image: ImageHandle(reader.u32()?),
+                    source: reader.rect()?,
+                    destination: reader.rect()?,
+                },
+                opcode => return Err(DecodeError::UnknownOpcode { opcode, offset }),
+            };
+            commands.push(command);
+        }
+        Ok(commands)
+    }
+}
+
+fn push_f32s(bytes: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn push_f32_slice(bytes: &mut Vec<u8>, values: &[f32]) {
+    bytes.extend_from_slice(&(values.len() as u32).to_le_bytes());
+    push_f32s(bytes, values);
+}
+
+fn push_str(bytes: &mut Vec<u8>, value: &str) {
+    bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let bytes =
+            self.bytes
+                .get(self.offset..self.offset + N)
+                .ok_or(DecodeError::UnexpectedEnd {
+                    offset: self.offset,
+                })?;
+        self.offset += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, DecodeError> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    fn f32_slice(&mut self) -> Result<Vec<f32>, DecodeError> {
+        let count = self.u32()?;
+        (0..count).map(|_| self.f32()).collect()
+    }
+
+    fn rect(&mut self) -> Result<Rect, DecodeError> {
+        Ok(Rect::new(
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+        ))
+    }
+
+    fn str(&mut self) -> Result<String, DecodeError> {
+        let length = self.u32()? as usize;
+        let offset = self.offset;
+        let bytes = self
+            .bytes
+            .get(offset..offset + length)
+            .ok_or(DecodeError::UnexpectedEnd { offset })?;
+        self.offset += length;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidValue { offset })
+    }
+}
+
+thread_local! {
+    // The list the free drawing functions record into. It is submitted after each `Draw` event.
+    static DRAW_LIST: RefCell<DrawList> = const { RefCell::new(DrawList::new()) };
+}
+
+pub(crate) fn record(command: DrawCommand) {
+    DRAW_LIST.with(|draw_list| draw_list.borrow_mut().push(command));
+}
+
+/// Sends everything recorded since the last submit to the host.
+pub(crate) fn submit() {
+    DRAW_LIST.with(|draw_list| {
+        let mut draw_list = draw_list.borrow_mut();
+        if !draw_list.is_empty() {
+            let bytes = draw_list.as_bytes();
+            unsafe {
+                super::unsafe_external::submit(bytes.as_ptr(), bytes.len() as _);
+            }
+            draw_list.clear();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_round_trip() {
+        let commands = [
+            DrawCommand::SetColor {
+                r: 1,
+                g: 2,
+                b: 3,
+                a: 4,
+            },
+            DrawCommand::SetTransform {
+                a: 1.0,
+                b: 0.5,
+                c: -0.5,
+                d: 1.0,
+                e: 10.0,
+                f: -20.0,
+            },
+            DrawCommand::BeginPath,
+            DrawCommand::MoveTo { x: 0.0, y: 1.0 },
+            DrawCommand::LineTo { x: 2.0, y: 3.0 },
+            DrawCommand::Fill,
+            DrawCommand::SetLineWidth { width: 2.0 },
+            DrawCommand::SetLineCap {
+                cap: LineCap::Round,
+            },
+            DrawCommand::SetLineJoin {
+                join: LineJoin::Bevel,
+            },
+            DrawCommand::SetLineDash {
+                offset: 1.5,
+                segments: vec![4.0, 2.0],
+            },
+            DrawCommand::Stroke,
+            DrawCommand::QuadraticTo {
+                control_x: 1.0,
+                control_y: 2.0,
+                x: 3.0,
+                y: 4.0,
+            },
+            DrawCommand::BezierTo {
+                control_0_x: 1.0,
+                control_0_y: 2.0,
+                control_1_x: 3.0,
+                control_1_y: 4.0,
+                x: 5.0,
+                y: 6.0,
+            },
+            DrawCommand::Arc {
+                x: 1.0,
+                y: 2.0,
+                radius: 3.0,
+                start_angle: 0.5,
+                end_angle: 1.5,
+                counterclockwise: true,
+            },
+            DrawCommand::ArcTo {
+                x1: 1.0,
+                y1: 2.0,
+                x2: 3.0,
+                y2: 4.0,
+                radius: 5.0,
+            },
+            DrawCommand::ClosePath,
+            DrawCommand::DrawEllipse {
+                x: 1.0,
+                y: 2.0,
+                radius_x: 3.0,
+                radius_y: 4.0,
+                rotation: 0.5,
+            },
+            DrawCommand::SetFont {
+                family: "Inter".into(),
+                size: 16.0,
+            },
+            DrawCommand::SetTextAlign {
+                align: TextAlign::Center,
+            },
+            DrawCommand::SetTextBaseline {
+                baseline: TextBaseline::Middle,
+            },
+            DrawCommand::DrawText {
+                x: 1.0,
+                y: 2.0,
+                text: "Héllo".into(),
+            },
+            DrawCommand::DrawImage {
+                image: ImageHandle(7),
+                source: Rect::new(1.0, 2.0, 3.0, 4.0),
+                destination: Rect::new(5.0, 6.0, 7.0, 8.0),
+            },
+            DrawCommand::DrawCircle {
+                x: 1.0,
+                y: 2.0,
+                radius: 3.0,
+            },
+            DrawCommand::DrawRect {
+                x: 1.0,
+                y: 2.0,
+                width: 3.0,
+                height: 4.0,
+            },
+        ];
+
+        let mut draw_list = DrawList::new();
+        for command in commands.iter().cloned() {
+            draw_list.push(command);
+        }
+        assert_eq!(DrawList::decode(draw_list.as_bytes()).unwrap(), commands);
+        assert_eq!(
+            DrawList::decode(&draw_list.as_bytes()[..draw_list.as_bytes().len() - 1]),
+            Err(DecodeError::UnexpectedEnd {
+                offset: draw_list.as_bytes().len() - 4
+            })
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+/// A button in the DOM's "standard" gamepad layout.
+///
+/// The face buttons are named by position because their labels differ between controllers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Home,
+    /// A button past the standard layout. Holds the raw index.
+    Other(u32),
+}
+
+const STANDARD_BUTTONS: [GamepadButton; 17] = [
+    GamepadButton::South,
+    GamepadButton::East,
+    GamepadButton::West,
+    GamepadButton::North,
+    GamepadButton::LeftBumper,
+    GamepadButton::RightBumper,
+    GamepadButton::LeftTrigger,
+    GamepadButton::RightTrigger,
+    GamepadButton::Select,
+    GamepadButton::Start,
+    GamepadButton::LeftStick,
+    GamepadButton::RightStick,
+    GamepadButton::DPadUp,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::DPadRight,
+    GamepadButton::Home,
+];
+
+impl GamepadButton {
+    /// Converts an index into the DOM `Gamepad.buttons` array.
+    pub fn from_index(index: u32) -> Self {
+        STANDARD_BUTTONS
+            .get(index as usize)
+            .copied()
+            .unwrap_or(GamepadButton::Other(index))
+    }
+
+    pub fn index(self) -> u32 {
+        match self {
+            GamepadButton::Other(index) => index,
+            button => STANDARD_BUTTONS.iter().position(|b| *b == button).unwrap() as u32,
+        }
+    }
+}
+
+/// An axis in the DOM's "standard" gamepad layout. Axes go from -1 to 1, with positive values right and down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    /// An axis past the standard layout. Holds the raw index.
+    Other(u32),
+}
+
+impl GamepadAxis {
+    /// Converts an index into the DOM `Gamepad.axes` array.
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            0 => GamepadAxis::LeftX,
+            1 => GamepadAxis::LeftY,
+            2 => GamepadAxis::RightX,
+            3 => GamepadAxis::RightY,
+            _ => GamepadAxis::Other(index),
+        }
+    }
+
+    pub fn index(self) -> u32 {
+        match self {
+            GamepadAxis::LeftX => 0,
+            GamepadAxis::LeftY => 1,
+            GamepadAxis::RightX => 2,
+            GamepadAxis::RightY => 3,
+            GamepadAxis::Other(index) => index,
+        }
+    }
+}
+
+/// Everything reported about one gamepad so far.
+///
+/// Built only from events, so it is the same on every peer and safe to read in `Game::fixed_update`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GamepadState {
+    /// Buttons that are pressed, with how far they are pressed from 0 to 1.
+    buttons: BTreeMap<GamepadButton, f32>,
+    axes: BTreeMap<GamepadAxis, f32>,
+}
+
+impl GamepadState {
+    pub fn is_button_down(&self, button: GamepadButton) -> bool {
+        self.buttons.contains_key(&button)
+    }
+
+    /// From 0 to 1. Only triggers usually report values in between.
+    pub fn button_value(&self, button: GamepadButton) -> f32 {
+        self.buttons.get(&button).copied().unwrap_or(0.0)
+    }
+
+    /// Returns every button that is pressed, in a stable order.
+    pub fn buttons_down(&self) -> Vec<GamepadButton> {
+        self.buttons.keys().copied().collect()
+    }
+
+    /// Returns 0 for axes that have not been reported.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    pub fn left_stick(&self) -> [f32; 2] {
+        [self.axis(GamepadAxis::LeftX), self.axis(GamepadAxis::LeftY)]
+    }
+
+    pub fn right_stick(&self) -> [f32; 2] {
+        [
+            self.axis(GamepadAxis::RightX),
+            self.axis(GamepadAxis::RightY),
+        ]
+    }
+}
+
+thread_local! {
+    // Keyed by player and then by the host's gamepad index.
+    static GAMEPADS: RefCell<BTreeMap<u32, BTreeMap<u32, GamepadState>>> =
+        const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Returns `false` if the gamepad was already connected.
+pub(crate) fn connect_gamepad(player: u32, gamepad: u32) -> bool {
+    GAMEPADS.with(|gamepads| {
+        let mut gamepads = gamepads.borrow_mut();
+        let player_gamepads = gamepads.entry(player).or_default();
+        if player_gamepads.contains_key(&gamepad) {
+            return false;
+        }
+        player_gamepads.insert(gamepad, GamepadState::default());
+        true
+    })
+}
+
+/// Returns `false` if the gamepad was not connected.
+pub(crate) fn disconnect_gamepad(player: u32, gamepad: u32) -> bool {
+    GAMEPADS.with(|gamepads| {
+        let mut gamepads = gamepads.borrow_mut();
+        let Some(player_gamepads) = gamepads.get_mut(&player) else {
+            return false;
+        };
+        let removed = player_gamepads.remove(&gamepad).is_some();
+        if player_gamepads.is_empty() {
+            gamepads.remove(&player);
+        }
+        removed
+    })
+}
+
+pub(crate) fn remove_player_gamepads(player: u32) {
+    GAMEPADS.with(|gamepads| {
+        gamepads.borrow_mut().remove(&player);
+    });
+}
+
+/// Records a button's state. Returns `false` if whether it is pressed did not change,
+/// including when the gamepad is not connected.
+pub(crate) fn set_gamepad_button(
+    player: u32,
+    gamepad: u32,
+    button: GamepadButton,
+    pressed: bool,
+    value: f32,
+) -> bool {
+    update_gamepad(player, gamepad, |state| {
+        if pressed {
+            state.buttons.insert(button, value).is_none()
+        } else {
+            state.buttons.remove(&button).is_some()
+        }
+    })
+    .unwrap_or(false)
+}
+
+/// Returns `false` if the gamepad is not connected.
+pub(crate) fn set_gamepad_axis(player: u32, gamepad: u32, axis: GamepadAxis, value: f32) -> bool {
+    update_gamepad(player, gamepad, |state| {
+        state.axes.insert(axis, value);
+    })
+    .is_some()
+}
+
+fn update_gamepad<R>(
+    player: u32,
+    gamepad: u32,
+    f: impl FnOnce(&mut GamepadState) -> R,
+) -> Option<R> {
+    GAMEPADS.with(|gamepads| {
+        gamepads
+            .borrow_mut()
+            .get_mut(&player)
+            .and_then(|player_gamepads| player_gamepads.get_mut(&gamepad))
+            .map(f)
+    })
+}
+
+/// Returns a snapshot of one of `player`'s gamepads, or `None` if it is not connected.
+pub fn gamepad(player: u32, gamepad: u32) -> Option<GamepadState> {
+    GAMEPADS.with(|gamepads| {
+        gamepads
+            .borrow()
+            .get(&player)
+            .and_then(|player_gamepads| player_gamepads.get(&gamepad))
+            .cloned()
+    })
+}
+
+/// Returns the indices of `player`'s connected gamepads, in a stable order.
+pub fn gamepads(player: u32) -> Vec<u32> {
+    GAMEPADS.with(|gamepads| {
+        gamepads
+            .borrow()
+            .get(&player)
+            .map(|player_gamepads| player_gamepads.keys().copied().collect())
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamepad_state_follows_events() {
+        assert!(!set_gamepad_button(7, 0, GamepadButton::South, true, 1.0));
+        assert!(connect_gamepad(7, 0));
+        assert!(!connect_gamepad(7, 0));
+
+        assert!(set_gamepad_button(
+            7,
+            0,
+            GamepadButton::RightTrigger,
+            true,
+            0.25
+        ));
+        // Pressing a trigger further only changes its value.
+        assert!(!set_gamepad_button(
+            7,
+            0,
+            GamepadButton::RightTrigger,
+            true,
+            0.75
+        ));
+        assert!(set_gamepad_axis(7, 0, GamepadAxis::LeftX, -0.5));
+
+        let state = gamepad(7, 0).unwrap();
+        assert_eq!(state.button_value(GamepadButton::RightTrigger), 0.75);
+        assert_eq!(state.left_stick(), [-0.5, 0.0]);
+        assert_eq!(GamepadButton::from_index(7), GamepadButton::RightTrigger);
+        assert_eq!(GamepadButton::from_index(20).index(), 20);
+
+        assert!(set_gamepad_button(
+            7,
+            0,
+            GamepadButton::RightTrigger,
+            false,
+            0.0
+        ));
+        assert!(!gamepad(7, 0)
+            .unwrap()
+            .is_button_down(GamepadButton::RightTrigger));
+
+        assert!(disconnect_gamepad(7, 0));
+        assert_eq!(gamepads(7), []);
+    }
+}
@@ -0,0 +1,315 @@
+//! Recognizes multi-touch and timed gestures from pointer events.
+//!
+//! Time is measured in fixed updates so every player recognizes the same gestures at the same time.
+
+use std::collections::BTreeMap;
+
+use super::{PointerEvent, PointerPhase};
+
+/// How many fixed updates a pointer must be held still for a `LongPress`.
+pub const LONG_PRESS_UPDATES: u64 = 30;
+/// How many fixed updates may pass between the taps of a `DoubleTap`.
+pub const DOUBLE_TAP_UPDATES: u64 = 18;
+/// How far in screen pixels a pointer may drift and still count as held still.
+pub const TAP_SLOP: f32 = 10.0;
+
+/// Positions are screen coordinates, like `PointerEvent::screen_x` and `PointerEvent::screen_y`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    /// The midpoint of two pointers moved.
+    Pan {
+        player: u32,
+        delta: [f32; 2],
+    },
+    /// The distance between two pointers changed by `scale`. Above 1 means they moved apart.
+    Pinch {
+        player: u32,
+        center: [f32; 2],
+        scale: f32,
+    },
+    /// The line between two pointers turned clockwise by `angle` radians.
+    Rotate {
+        player: u32,
+        center: [f32; 2],
+        angle: f32,
+    },
+    LongPress {
+        player: u32,
+        pointer_id: u32,
+        position: [f32; 2],
+    },
+    DoubleTap {
+        player: u32,
+        position: [f32; 2],
+    },
+}
+
+struct TrackedPointer {
+    pointer_id: u32,
+    start: [f32; 2],
+    position: [f32; 2],
+    down_update: u64,
+    /// Set once the pointer can no longer be a tap or a long press.
+    disqualified: bool,
+}
+
+#[derive(Default)]
+struct PlayerGestures {
+    /// In the order they went down. The first two drive pan, pinch, and rotate.
+    pointers: Vec<TrackedPointer>,
+    last_tap: Option<([f32; 2], u64)>,
+}
+
+/// Feed this every pointer event and every fixed update, and it returns the gestures they complete.
+#[derive(Default)]
+pub struct GestureRecognizer {
+    update: u64,
+    players: BTreeMap<u32, PlayerGestures>,
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pointer(&mut self, event: &PointerEvent) -> Vec<Gesture> {
+        let mut gestures = Vec::new();
+        let player = event.player;
+        let position = [event.screen_x, event.screen_y];
+        let state = self.players.entry(player).or_default();
+        let index = state
+            .pointers
+            .iter()
+            .position(|pointer| pointer.pointer_id == event.pointer_id);
+
+        match event.phase {
+            PointerPhase::Down => {
+                if let Some(index) = index {
+                    state.pointers.remove(index);
+                }
+                state.pointers.push(TrackedPointer {
+                    pointer_id: event.pointer_id,
+                    start: position,
+                    position,
+                    down_update: self.update,
+                    disqualified: false,
+                });
+                // Taps and long presses are single pointer gestures.
+                if state.pointers.len() > 1 {
+                    state
+                        .pointers
+                        .iter_mut()
+                        .for_each(|pointer| pointer.disqualified = true);
+                }
+            }
+            PointerPhase::Move => {
+                let Some(index) = index else {
+                    return gestures;
+                };
+                let before = two_pointers(&state.pointers);
+
+                let pointer = &mut state.pointers[index];
+                pointer.position = position;
+                if distance(pointer.start, position) > TAP_SLOP {
+                    pointer.disqualified = true;
+                }
+
+                if let (Some((a0, b0)), Some((a1, b1))) = (before, two_pointers(&state.pointers)) {
+                    let center0 = midpoint(a0, b0);
+                    let center1 = midpoint(a1, b1);
+                    gestures.push(Gesture::Pan {
+                        player,
+                        delta: [center1[0] - center0[0], center1[1] - center0[1]],
+                    });
+
+                    let distance0 = distance(a0, b0);
+                    if distance0 > 0.0 {
+                        gestures.push(Gesture::Pinch {
+                            player,
+                            center: center1,
+                            scale: distance(a1, b1) / distance0,
+                        });
+                    }
+
+                    let angle = angle(a1, b1) - angle(a0, b0);
+                    // Keep the change in the -π to π range when the line crosses the negative x axis.
+                    let angle = (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+                        - std::f32::consts::PI;
+                    gestures.push(Gesture::Rotate {
+                        player,
+                        center: center1,
+                        angle,
+                    });
+                }
+            }
+            PointerPhase::Up => {
+                let Some(index) = index else {
+                    return gestures;
+                };
+                let pointer = state.pointers.remove(index);
+                let is_tap =
+                    !pointer.disqualified && self.update - pointer.down_update < LONG_PRESS_UPDATES;
+                if is_tap {
+                    match state.last_tap {
+                        Some((last_position, last_update))
+                            if self.update - last_update <= DOUBLE_TAP_UPDATES
+                                && distance(last_position, position) <= TAP_SLOP * 2.0 =>
+                        {
+                            state.last_tap = None;
+                            gestures.push(Gesture::DoubleTap { player, position });
+                        }
+                        _ => state.last_tap = Some((position, self.update)),
+                    }
+                }
+            }
+            PointerPhase::Cancel => {
+                if let Some(index) = index {
+                    state.pointers.remove(index);
+                }
+                state.last_tap = None;
+            }
+        }
+        gestures
+    }
+
+    /// Advances the clock timed gestures are measured with. Call this once per `Game::fixed_update`.
+    pub fn fixed_update(&mut self) -> Vec<Gesture> {
+        self.update += 1;
+        let update = self.update;
+
+        let mut gestures = Vec::new();
+        for (&player, state) in self.players.iter_mut() {
+            for pointer in state.pointers.iter_mut() {
+                if !pointer.disqualified && update - pointer.down_update >= LONG_PRESS_UPDATES {
+                    pointer.disqualified = true;
+                    gestures.push(Gesture::LongPress {
+                        player,
+                        pointer_id: pointer.pointer_id,
+                        position: pointer.position,
+                    });
+                }
+            }
+        }
+
+        // Forget players who are not doing anything so the map does not grow forever.
+        self.players.retain(|_, state| {
+            !state.pointers.is_empty()
+                || state
+                    .last_tap
+                    .is_some_and(|(_, last_update)| update - last_update <= DOUBLE_TAP_UPDATES)
+        });
+        gestures
+    }
+
+    pub fn remove_player(&mut self, player: u32) {
+        self.players.remove(&player);
+    }
+}
+
+fn two_pointers(pointers: &[TrackedPointer]) -> Option<([f32; 2], [f32; 2])> {
+    match pointers {
+        [a, b, ..] => Some((a.position, b.position)),
+        _ => None,
+    }
+}
+
+fn midpoint(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0]
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
+
+/// Uses `libm` because `f32::atan2` can differ between platforms, and games use this angle to change the simulation.
+fn angle(a: [f32; 2], b: [f32; 2]) -> f32 {
+    libm::atan2f(b[1] - a[1], b[0] - a[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_engine::PointerDetails;
+
+    fn event(pointer_id: u32, phase: PointerPhase, x: f32, y: f32) -> PointerEvent {
+        PointerEvent {
+            player: 1,
+            pointer_id,
+            phase,
+            details: PointerDetails::default(),
+            x,
+            y,
+            screen_x: x,
+            screen_y: y,
+        }
+    }
+
+    #[test]
+    fn two_pointers_pinch_and_rotate() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.pointer(&event(0, PointerPhase::Down, 0.0, 0.0));
+        recognizer.pointer(&event(1, PointerPhase::Down, 10.0, 0.0));
+
+        // Swing the second pointer a quarter turn clockwise and twice as far away.
+        let gestures = recognizer.pointer(&event(1, PointerPhase::Move, 0.0, 20.0));
+        assert_eq!(
+            gestures[..2],
+            [
+                Gesture::Pan {
+                    player: 1,
+                    delta: [-5.0, 10.0]
+                },
+                Gesture::Pinch {
+                    player: 1,
+                    center: [0.0, 10.0],
+                    scale: 2.0
+                },
+            ]
+        );
+        let Gesture::Rotate { angle, .. } = gestures[2] else {
+            panic!("Expected a rotation, got {:?}", gestures[2]);
+        };
+        assert!((angle - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+
+        // Neither pointer can long press once both were down.
+        for _ in 0..LONG_PRESS_UPDATES {
+            assert!(recognizer.fixed_update().is_empty());
+        }
+    }
+
+    #[test]
+    fn taps_and_long_presses() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.pointer(&event(0, PointerPhase::Down, 5.0, 5.0));
+        assert!(recognizer
+            .pointer(&event(0, PointerPhase::Up, 5.0, 5.0))
+            .is_empty());
+        recognizer.fixed_update();
+        recognizer.pointer(&event(0, PointerPhase::Down, 7.0, 5.0));
+        assert_eq!(
+            recognizer.pointer(&event(0, PointerPhase::Up, 7.0, 5.0)),
+            [Gesture::DoubleTap {
+                player: 1,
+                position: [7.0, 5.0]
+            }]
+        );
+
+        recognizer.pointer(&event(2, PointerPhase::Down, 50.0, 50.0));
+        let mut gestures = Vec::new();
+        for _ in 0..LONG_PRESS_UPDATES * 2 {
+            gestures.extend(recognizer.fixed_update());
+        }
+        assert_eq!(
+            gestures,
+            [Gesture::LongPress {
+                player: 1,
+                pointer_id: 2,
+                position: [50.0, 50.0]
+            }]
+        );
+        // A long press is not also a tap.
+        assert!(recognizer
+            .pointer(&event(2, PointerPhase::Up, 50.0, 50.0))
+            .is_empty());
+    }
+}
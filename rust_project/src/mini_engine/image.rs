@@ -0,0 +1,278 @@
+use std::cell::RefCell;
+
+use super::draw_list::{record, DrawCommand};
+
+/// Identifies an image requested with `load_image`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ImageHandle(pub u32);
+
+/// A rectangle in pixels, with its origin at the top left.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+struct Image {
+    path: String,
+    size: Option<(u32, u32)>,
+}
+
+thread_local! {
+    // Indexed by `ImageHandle`.
+    static IMAGES: RefCell<Vec<Image>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Asks the host to load the image at `path`, relative to the website's root.
+///
+/// Returns immediately. An `Event::ImageLoaded` is sent once the image is ready, and until then
+/// drawing the image does nothing. Loading the same path twice returns the same handle.
+///
+/// Handles are assigned in call order, so call this from `Game::init` or in response to an
+/// event so every player assigns the same handles.
+pub fn load_image(path: &str) -> ImageHandle {
+    IMAGES.with(|images| {
+        let mut images = images.borrow_mut();
+        if let Some(index) = images.iter().position(|image| image.path == path) {
+            return ImageHandle(index as u32);
+        }
+
+        let handle = ImageHandle(images.len() as u32);
+        images.push(Image {
+            path: path.to_string(),
+            size: None,
+        });
+        request_image(handle, path);
+        handle
+    })
+}
+
+fn request_image(image: ImageHandle, path: &str) {
+    unsafe {
+        super::unsafe_external::load_image(image.0, path.as_ptr(), path.len() as _);
+    }
+}
+
+/// Asks the host to load every image again. Called when a player joins, because a peer that joins late
+/// starts from the others' memory and its host never saw the original requests.
+/// Hosts ignore requests for images they already loaded.
+pub(crate) fn request_images() {
+    IMAGES.with(|images| {
+        for (index, image) in images.borrow().iter().enumerate() {
+            request_image(ImageHandle(index as u32), &image.path);
+        }
+    });
+}
+
+/// Records the size of a loaded image.
+/// Returns `false` if the image was already loaded, which happens because every player's host reports it.
+pub(crate) fn set_image_loaded(image: ImageHandle, width: u32, height: u32) -> bool {
+    IMAGES.with(
+        |images| match images.borrow_mut().get_mut(image.0 as usize) {
+            Some(image) if image.size.is_none() => {
+                image.size = Some((width, height));
+                true
+            }
+            _ => false,
+        },
+    )
+}
+
+/// Returns the width and height of an image, or `None` if it has not loaded yet.
+pub fn image_size(image: ImageHandle) -> Option<(u32, u32)> {
+    IMAGES.with(|images| {
+        images
+            .borrow()
+            .get(image.0 as usize)
+            .and_then(|image| image.size)
+    })
+}
+
+/// Draws the whole image stretched to fill `destination`.
+pub fn draw_image(image: ImageHandle, destination: Rect) {
+    if let Some((width, height)) = image_size(image) {
+        let source = Rect::new(0.0, 0.0, width as f32, height as f32);
+        draw_image_region(image, source, destination);
+    }
+}
+
+/// Draws the `source` part of an image stretched to fill `destination`.
+pub fn draw_image_region(image: ImageHandle, source: Rect, destination: Rect) {
+    record(DrawCommand::DrawImage {
+        image,
+        source,
+        destination,
+    });
+}
+
+/// An image split into equally sized frames, numbered left to right and then top to bottom.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpriteSheet {
+    pub image: ImageHandle,
+    pub frame_width: u32,
+    pub frame_height: u32,
+}
+
+impl SpriteSheet {
+    pub const fn new(image: ImageHandle, frame_width: u32, frame_height: u32) -> Self {
+        Self {
+            image,
+            frame_width,
+            frame_height,
+        }
+    }
+
+    /// Returns how many columns and rows of frames fit in the image, or `None` if it has not loaded yet.
+    fn grid(&self) -> Option<(u32, u32)> {
+        let (width, height) = image_size(self.image)?;
+        Some((
+            width / self.frame_width.max(1),
+            height / self.frame_height.max(1),
+        ))
+    }
+
+    /// Returns 0 until the image has loaded.
+    pub fn frame_count(&self) -> u32 {
+        self.grid().map_or(0, |(columns, rows)| columns * rows)
+    }
+
+    /// Returns the part of the image `frame` covers, or `None` if there is no such frame yet.
+    pub fn frame_rect(&self, frame: u32) -> Option<Rect> {
+        let (columns, rows) = self.grid()?;
+        if frame >= columns * rows {
+            return None;
+        }
+        Some(Rect::new(
+            ((frame % columns) * self.frame_width) as f32,
+            ((frame / columns) * self.frame_height) as f32,
+            self.frame_width as f32,
+            self.frame_height as f32,
+        ))
+    }
+
+    /// Draws `frame` stretched to fill `destination`.
+    /// Frames past the end wrap around, so an animation can pass an ever increasing counter.
+    pub fn draw_frame(&self, frame: u32, destination: Rect) {
+        let frame_count = self.frame_count();
+        if frame_count == 0 {
+            return;
+        }
+        if let Some(source) = self.frame_rect(frame % frame_count) {
+            draw_image_region(self.image, source, destination);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_engine::native::{take_image_requests, Harness};
+    use crate::mini_engine::rasterizer::Canvas;
+    use crate::mini_engine::{Event, Game};
+
+    /// A 50 by 20 pixel image, written by `write_sprites`.
+    fn sprites_path() -> String {
+        let path = std::env::temp_dir().join("mini_engine_sprites.png");
+        path.to_str().unwrap().to_string()
+    }
+
+    fn write_sprites() {
+        static WRITE: std::sync::Once = std::sync::Once::new();
+        WRITE.call_once(|| Canvas::new(50, 20).save_png(sprites_path()).unwrap());
+    }
+
+    struct Sprites {
+        sheet: SpriteSheet,
+        loaded: Vec<ImageHandle>,
+    }
+
+    impl Game for Sprites {
+        fn init() -> Self {
+            Self {
+                sheet: SpriteSheet::new(load_image(&sprites_path()), 16, 8),
+                loaded: Vec::new(),
+            }
+        }
+
+        fn on_image_loaded(&mut self, image: ImageHandle) {
+            self.loaded.push(image);
+        }
+
+        fn draw(&mut self) {
+            draw_image(self.sheet.image, Rect::new(0.0, 0.0, 100.0, 40.0));
+            self.sheet.draw_frame(7, Rect::new(1.0, 2.0, 3.0, 4.0));
+        }
+    }
+
+    #[test]
+    fn sprite_sheet_frames_are_numbered_by_row() {
+        let sheet = SpriteSheet::new(load_image("sprites.png"), 16, 8);
+        assert_eq!(sheet.frame_count(), 0);
+        assert_eq!(sheet.frame_rect(0), None);
+
+        // The leftover pixels on the right and bottom aren't enough for another frame.
+        set_image_loaded(sheet.image, 50, 20);
+        assert_eq!(sheet.grid(), Some((3, 2)));
+        assert_eq!(sheet.frame_count(), 6);
+        assert_eq!(sheet.frame_rect(0), Some(Rect::new(0.0, 0.0, 16.0, 8.0)));
+        assert_eq!(sheet.frame_rect(4), Some(Rect::new(16.0, 8.0, 16.0, 8.0)));
+        assert_eq!(sheet.frame_rect(5), Some(Rect::new(32.0, 8.0, 16.0, 8.0)));
+        assert_eq!(sheet.frame_rect(6), None);
+        assert_eq!(sheet.frame_rect(u32::MAX), None);
+    }
+
+    #[test]
+    fn images_draw_once_loaded() {
+        write_sprites();
+        let mut harness = Harness::<Sprites>::new();
+        let image = harness.game.sheet.image;
+        assert_eq!(harness.draw(), []);
+
+        harness.load_images();
+        assert_eq!(harness.game.loaded, [image]);
+        assert_eq!(image_size(image), Some((50, 20)));
+        assert_eq!(
+            harness.draw(),
+            [
+                DrawCommand::DrawImage {
+                    image,
+                    source: Rect::new(0.0, 0.0, 50.0, 20.0),
+                    destination: Rect::new(0.0, 0.0, 100.0, 40.0),
+                },
+                // Frame 7 wraps around to frame 1.
+                DrawCommand::DrawImage {
+                    image,
+                    source: Rect::new(16.0, 0.0, 16.0, 8.0),
+                    destination: Rect::new(1.0, 2.0, 3.0, 4.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn joining_players_request_images_again() {
+        write_sprites();
+        let mut harness = Harness::<Sprites>::new();
+        harness.load_images();
+
+        harness.send(Event::PlayerJoined { player: 1 });
+        assert_eq!(
+            take_image_requests(),
+            [(harness.game.sheet.image, sprites_path())]
+        );
+        assert_eq!(harness.game.loaded.len(), 1);
+    }
+}
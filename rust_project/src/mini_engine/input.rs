@@ -0,0 +1,254 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hash;
+
+use super::{Key, PointerEvent, PointerKind, PointerPhase, StateHasher};
+
+/// How much of the movement in each fixed update goes into `PointerState::velocity`.
+/// The rest is the previous velocity, so a fixed update without movement only halves it.
+const VELOCITY_SMOOTHING: f32 = 0.5;
+
+/// What is known about one of a player's pointers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerState {
+    pub pointer_id: u32,
+    pub kind: PointerKind,
+    /// In the same coordinates as `PointerEvent::x` and `PointerEvent::y`.
+    pub position: [f32; 2],
+    /// Where the pointer was at the end of the previous fixed update.
+    pub previous_position: [f32; 2],
+    /// How far the pointer moves per fixed update, smoothed over the last few fixed updates.
+    ///
+    /// Only updated at the end of each fixed update, so it is still meaningful when the pointer
+    /// is released without moving since the previous one.
+    pub velocity: [f32; 2],
+    pub screen_position: [f32; 2],
+    pub is_down: bool,
+    /// Pressed since the previous fixed update. Stays set until the end of the next fixed update.
+    pub just_pressed: bool,
+    /// Released or canceled since the previous fixed update. Stays set until the end of the next fixed update.
+    pub just_released: bool,
+}
+
+/// One player's pointers and keys, built only from events so it is the same on every peer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputState {
+    /// By pointer id. Mice are kept after they are released because they keep hovering,
+    /// but touches and pens are removed at the end of the fixed update after they lift.
+    pub pointers: BTreeMap<u32, PointerState>,
+    pub keys_down: BTreeSet<Key>,
+    /// Keys pressed since the previous fixed update, like `PointerState::just_pressed`.
+    pub keys_just_pressed: BTreeSet<Key>,
+    pub keys_just_released: BTreeSet<Key>,
+}
+
+impl InputState {
+    pub fn pointer(&self, pointer_id: u32) -> Option<&PointerState> {
+        self.pointers.get(&pointer_id)
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn is_key_just_pressed(&self, key: Key) -> bool {
+        self.keys_just_pressed.contains(&key)
+    }
+
+    pub fn is_key_just_released(&self, key: Key) -> bool {
+        self.keys_just_released.contains(&key)
+    }
+}
+
+thread_local! {
+    static INPUT: RefCell<BTreeMap<u32, InputState>> = const { RefCell::new(BTreeMap::new()) };
+    static LOCAL_PLAYER: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+fn update_input(player: u32, f: impl FnOnce(&mut InputState)) {
+    INPUT.with(|input| f(input.borrow_mut().entry(player).or_default()));
+}
+
+pub(crate) fn record_pointer(event: &PointerEvent) {
+    update_input(event.player, |input| {
+        let position = [event.x, event.y];
+        let pointer = input
+            .pointers
+            .entry(event.pointer_id)
+            .or_insert(PointerState {
+                pointer_id: event.pointer_id,
+                kind: event.details.kind,
+                position,
+                previous_position: position,
+                velocity: [0.0, 0.0],
+                screen_position: [event.screen_x, event.screen_y],
+                is_down: false,
+                just_pressed: false,
+                just_released: false,
+            });
+        pointer.kind = event.details.kind;
+        pointer.position = position;
+        pointer.screen_position = [event.screen_x, event.screen_y];
+        match event.phase {
+            PointerPhase::Down => {
+                pointer.is_down = true;
+                pointer.just_pressed = true;
+            }
+            PointerPhase::Up | PointerPhase::Cancel => {
+                pointer.is_down = false;
+                pointer.just_released = true;
+            }
+            PointerPhase::Move => {}
+        }
+    });
+}
+
+/// Records a key press or release. Returns `false` if the key was already in that state.
+pub(crate) fn record_key(player: u32, key: Key, down: bool) -> bool {
+    let mut changed = false;
+    update_input(player, |input| {
+        if down {
+            changed = input.keys_down.insert(key);
+            if changed {
+                input.keys_just_pressed.insert(key);
+            }
+        } else {
+            changed = input.keys_down.remove(&key);
+            if changed {
+                input.keys_just_released.insert(key);
+            }
+        }
+    });
+    changed
+}
+
+/// Clears what only lasts until the end of a fixed update. Called after `Game::fixed_update`.
+pub(crate) fn end_fixed_update() {
+    INPUT.with(|input| {
+        for input in input.borrow_mut().values_mut() {
+            input
+                .pointers
+                .retain(|_, pointer| pointer.is_down || pointer.kind == PointerKind::Mouse);
+            for pointer in input.pointers.values_mut() {
+                for i in 0..2 {
+                    let moved = pointer.position[i] - pointer.previous_position[i];
+                    pointer.velocity[i] += (moved - pointer.velocity[i]) * VELOCITY_SMOOTHING;
+                }
+                pointer.previous_position = pointer.position;
+                pointer.just_pressed = false;
+                pointer.just_released = false;
+            }
+            input.keys_just_pressed.clear();
+            input.keys_just_released.clear();
+        }
+    });
+}
+
+pub(crate) fn remove_player_input(player: u32) {
+    INPUT.with(|input| {
+        input.borrow_mut().remove(&player);
+    });
+}
+
+pub(crate) fn hash_input(hasher: &mut StateHasher) {
+    INPUT.with(|input| {
+        for (player, input) in input.borrow().iter() {
+            player.hash(hasher);
+            input.pointers.len().hash(hasher);
+            for pointer in input.pointers.values() {
+                pointer.pointer_id.hash(hasher);
+                (pointer.kind as u8).hash(hasher);
+                for value in pointer
+                    .position
+                    .iter()
+                    .chain(&pointer.previous_position)
+                    .chain(&pointer.velocity)
+                    .chain(&pointer.screen_position)
+                {
+                    hasher.write_f32(*value);
+                }
+                (pointer.is_down, pointer.just_pressed, pointer.just_released).hash(hasher);
+            }
+            input.keys_down.hash(hasher);
+            input.keys_just_pressed.hash(hasher);
+            input.keys_just_released.hash(hasher);
+        }
+    });
+}
+
+/// Calls `f` with `player`'s input without copying it, or with `None` if nothing has been seen from them.
+pub(crate) fn read_input<R>(player: u32, f: impl FnOnce(Option<&InputState>) -> R) -> R {
+    INPUT.with(|input| f(input.borrow().get(&player)))
+}
+
+/// Returns a snapshot of `player`'s pointers and keys.
+pub fn input(player: u32) -> InputState {
+    INPUT.with(|input| input.borrow().get(&player).cloned().unwrap_or_default())
+}
+
+/// Returns one of `player`'s pointers, or `None` if it has not been seen or was lifted.
+pub fn pointer_state(player: u32, pointer_id: u32) -> Option<PointerState> {
+    INPUT.with(|input| {
+        input
+            .borrow()
+            .get(&player)
+            .and_then(|input| input.pointers.get(&pointer_id).copied())
+    })
+}
+
+pub(crate) fn set_local_player(player: u32) {
+    LOCAL_PLAYER.with(|local_player| local_player.set(Some(player)));
+}
+
+/// Returns the player whose screen is being drawn to.
+///
+/// Only meaningful during `Game::draw`, which runs separately for every player.
+pub fn local_player() -> Option<u32> {
+    LOCAL_PLAYER.with(|local_player| local_player.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_engine::PointerDetails;
+
+    fn event(phase: PointerPhase, kind: PointerKind, x: f32) -> PointerEvent {
+        PointerEvent {
+            player: 2,
+            pointer_id: 5,
+            phase,
+            details: PointerDetails {
+                kind,
+                ..Default::default()
+            },
+            x,
+            y: 0.0,
+            screen_x: x,
+            screen_y: 0.0,
+        }
+    }
+
+    #[test]
+    fn pointer_flags_last_one_fixed_update() {
+        record_pointer(&event(PointerPhase::Down, PointerKind::Touch, 1.0));
+        record_pointer(&event(PointerPhase::Move, PointerKind::Touch, 3.0));
+        let pointer = pointer_state(2, 5).unwrap();
+        assert!(pointer.is_down && pointer.just_pressed);
+        assert_eq!(pointer.velocity, [0.0, 0.0]);
+
+        end_fixed_update();
+        let pointer = pointer_state(2, 5).unwrap();
+        assert!(!pointer.just_pressed);
+        assert_eq!(pointer.velocity, [1.0, 0.0]);
+
+        // Standing still slows the pointer down instead of stopping it at once.
+        end_fixed_update();
+        assert_eq!(pointer_state(2, 5).unwrap().velocity, [0.5, 0.0]);
+
+        record_pointer(&event(PointerPhase::Up, PointerKind::Touch, 3.0));
+        assert!(pointer_state(2, 5).unwrap().just_released);
+        // A lifted touch is forgotten once the fixed update has had a chance to see it.
+        end_fixed_update();
+        assert_eq!(pointer_state(2, 5), None);
+    }
+}
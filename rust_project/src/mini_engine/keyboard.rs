@@ -0,0 +1,383 @@
+use super::input::read_input;
+
+/// A physical key, as reported by the host's legacy `keyCode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Key {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadMultiply,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadDecimal,
+    NumpadDivide,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Left,
+    Up,
+    Right,
+    Down,
+    Backspace,
+    Tab,
+    Enter,
+    Shift,
+    Control,
+    Alt,
+    Meta,
+    Pause,
+    CapsLock,
+    Escape,
+    Space,
+    PageUp,
+    PageDown,
+    End,
+    Home,
+    PrintScreen,
+    Insert,
+    Delete,
+    ContextMenu,
+    NumLock,
+    ScrollLock,
+    Semicolon,
+    Equal,
+    Comma,
+    Minus,
+    Period,
+    Slash,
+    Backquote,
+    BracketLeft,
+    Backslash,
+    BracketRight,
+    Quote,
+    /// A key without a known mapping. Holds the raw `keyCode`.
+    Unknown(u32),
+}
+
+const LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+const DIGITS: [Key; 10] = [
+    Key::Digit0,
+    Key::Digit1,
+    Key::Digit2,
+    Key::Digit3,
+    Key::Digit4,
+    Key::Digit5,
+    Key::Digit6,
+    Key::Digit7,
+    Key::Digit8,
+    Key::Digit9,
+];
+
+const NUMPAD_DIGITS: [Key; 10] = [
+    Key::Numpad0,
+    Key::Numpad1,
+    Key::Numpad2,
+    Key::Numpad3,
+    Key::Numpad4,
+    Key::Numpad5,
+    Key::Numpad6,
+    Key::Numpad7,
+    Key::Numpad8,
+    Key::Numpad9,
+];
+
+const FUNCTION_KEYS: [Key; 12] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+impl Key {
+    /// Converts a legacy DOM `KeyboardEvent.keyCode` to a `Key`.
+    /// This also accounts for the few codes where Firefox differs from other browsers.
+    pub fn from_key_code(key_code: u32) -> Self {
+        match key_code {
+            8 => Key::Backspace,
+            9 => Key::Tab,
+            13 => Key::Enter,
+            16 => Key::Shift,
+            17 => Key::Control,
+            18 => Key::Alt,
+            19 => Key::Pause,
+            20 => Key::CapsLock,
+            27 => Key::Escape,
+            32 => Key::Space,
+            33 => Key::PageUp,
+            34 => Key::PageDown,
+            35 => Key::End,
+            36 => Key::Home,
+            37 => Key::Left,
+            38 => Key::Up,
+            39 => Key::Right,
+            40 => Key::Down,
+            44 => Key::PrintScreen,
+            45 => Key::Insert,
+            46 => Key::Delete,
+            48..=57 => DIGITS[(key_code - 48) as usize],
+            59 | 186 => Key::Semicolon,
+            61 | 187 => Key::Equal,
+            65..=90 => LETTERS[(key_code - 65) as usize],
+            91 | 92 | 224 => Key::Meta,
+            93 => Key::ContextMenu,
+            96..=105 => NUMPAD_DIGITS[(key_code - 96) as usize],
+            106 => Key::NumpadMultiply,
+            107 => Key::NumpadAdd,
+            109 => Key::NumpadSubtract,
+            110 => Key::NumpadDecimal,
+            111 => Key::NumpadDivide,
+            112..=123 => FUNCTION_KEYS[(key_code - 112) as usize],
+            144 => Key::NumLock,
+            145 => Key::ScrollLock,
+            173 | 189 => Key::Minus,
+            188 => Key::Comma,
+            190 => Key::Period,
+            191 => Key::Slash,
+            192 => Key::Backquote,
+            219 => Key::BracketLeft,
+            220 => Key::Backslash,
+            221 => Key::BracketRight,
+            222 => Key::Quote,
+            _ => Key::Unknown(key_code),
+        }
+    }
+
+    pub fn is_modifier(self) -> bool {
+        matches!(self, Key::Shift | Key::Control | Key::Alt | Key::Meta)
+    }
+}
+
+/// Which modifier keys a player is currently holding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+impl Modifiers {
+    /// True if either Control or Meta is held, which is what most shortcuts expect
+    /// across Windows, Linux, and Mac.
+    pub fn command(&self) -> bool {
+        self.control || self.meta
+    }
+}
+
+/// Returns true if `player` is currently holding `key`.
+pub fn is_key_down(player: u32, key: Key) -> bool {
+    read_input(player, |input| {
+        input.is_some_and(|input| input.is_key_down(key))
+    })
+}
+
+/// Returns every key `player` is currently holding, in a stable order.
+pub fn keys_down(player: u32) -> Vec<Key> {
+    read_input(player, |input| {
+        input
+            .map(|input| input.keys_down.iter().copied().collect())
+            .unwrap_or_default()
+    })
+}
+
+pub fn modifiers(player: u32) -> Modifiers {
+    Modifiers {
+        shift: is_key_down(player, Key::Shift),
+        control: is_key_down(player, Key::Control),
+        alt: is_key_down(player, Key::Alt),
+        meta: is_key_down(player, Key::Meta),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_engine::{dispatch, Event, Game};
+
+    #[test]
+    fn key_codes_map_to_keys() {
+        // Codes `index.ts` passes through from `KeyboardEvent.keyCode`, including Firefox's variants.
+        let table = [
+            (8, Key::Backspace),
+            (16, Key::Shift),
+            (17, Key::Control),
+            (18, Key::Alt),
+            (91, Key::Meta),
+            (92, Key::Meta),
+            (224, Key::Meta),
+            (32, Key::Space),
+            (37, Key::Left),
+            (46, Key::Delete),
+            (48, Key::Digit0),
+            (57, Key::Digit9),
+            (65, Key::A),
+            (69, Key::E),
+            (81, Key::Q),
+            (90, Key::Z),
+            (96, Key::Numpad0),
+            (112, Key::F1),
+            (123, Key::F12),
+            (59, Key::Semicolon),
+            (186, Key::Semicolon),
+            (61, Key::Equal),
+            (187, Key::Equal),
+            (173, Key::Minus),
+            (189, Key::Minus),
+            (222, Key::Quote),
+            (250, Key::Unknown(250)),
+        ];
+        for (key_code, key) in table {
+            assert_eq!(Key::from_key_code(key_code), key, "keyCode {}", key_code);
+        }
+        assert!(Key::from_key_code(224).is_modifier());
+        assert!(!Key::from_key_code(65).is_modifier());
+    }
+
+    #[derive(Default)]
+    struct Presses {
+        down: Vec<(u32, Key)>,
+    }
+
+    impl Game for Presses {
+        fn init() -> Self {
+            Self::default()
+        }
+
+        fn on_key_down(&mut self, player: u32, key: Key) {
+            self.down.push((player, key));
+        }
+    }
+
+    #[test]
+    fn key_state_is_tracked_per_player() {
+        let mut game = Presses::init();
+        let (a, b) = (4, 5);
+        dispatch(
+            &mut game,
+            Event::KeyDown {
+                player: a,
+                key: Key::Shift,
+            },
+        );
+        // Key repeat sends the press again, but games only see the first one.
+        dispatch(
+            &mut game,
+            Event::KeyDown {
+                player: a,
+                key: Key::Shift,
+            },
+        );
+        dispatch(
+            &mut game,
+            Event::KeyDown {
+                player: b,
+                key: Key::Meta,
+            },
+        );
+        assert_eq!(game.down, [(a, Key::Shift), (b, Key::Meta)]);
+        assert!(modifiers(a).shift && !modifiers(a).command());
+        assert!(modifiers(b).command());
+
+        dispatch(
+            &mut game,
+            Event::KeyUp {
+                player: a,
+                key: Key::Shift,
+            },
+        );
+        assert!(!is_key_down(a, Key::Shift));
+        assert_eq!(keys_down(b), [Key::Meta]);
+
+        // A player that leaves lets go of everything.
+        dispatch(&mut game, Event::PlayerLeft { player: b });
+        assert!(keys_down(b).is_empty());
+    }
+}
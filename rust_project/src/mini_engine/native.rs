@@ -0,0 +1,229 @@
+//! Stands in for the browser host when compiling for something other than Wasm.
+//!
+//! Submitted draw lists are decoded and recorded instead of rendered and log messages are printed,
+//! so games can be driven and inspected from `cargo test`.
+//! Images are PNGs loaded from the website's `dist` folder when the harness asks for them.
+//!
+//! Only the functions standing in for the host's imports are built outside of tests.
+
+use std::cell::RefCell;
+#[cfg(test)]
+use std::collections::BTreeMap;
+#[cfg(test)]
+use std::path::PathBuf;
+#[cfg(test)]
+use std::rc::Rc;
+
+#[cfg(test)]
+use super::rasterizer::{load_png, Canvas};
+#[cfg(test)]
+use super::{dispatch, dispatch_or_init, state_hash, Event, Game, Viewport};
+use super::{DrawCommand, DrawList, ImageHandle};
+
+thread_local! {
+    static DRAW_COMMANDS: RefCell<Vec<DrawCommand>> = const { RefCell::new(Vec::new()) };
+    static LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static PENDING_IMAGES: RefCell<Vec<(ImageHandle, String)>> = const { RefCell::new(Vec::new()) };
+    static STATE_HASH_REPORTS: RefCell<Vec<(u32, u64)>> = const { RefCell::new(Vec::new()) };
+    static STATE_HASH_MISMATCHES: RefCell<Vec<(u32, u32)>> = const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(test)]
+thread_local! {
+    static IMAGES: RefCell<BTreeMap<ImageHandle, Rc<Canvas>>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Returns and clears every draw command recorded so far on this thread.
+#[cfg(test)]
+pub fn take_draw_commands() -> Vec<DrawCommand> {
+    DRAW_COMMANDS.with(|commands| std::mem::take(&mut *commands.borrow_mut()))
+}
+
+/// Returns and clears every message logged so far on this thread.
+#[cfg(test)]
+pub fn take_log() -> Vec<String> {
+    LOG.with(|log| std::mem::take(&mut *log.borrow_mut()))
+}
+
+/// Returns and clears every tick and hash passed to the host by `report_state_hash` so far on this thread.
+#[cfg(test)]
+pub fn take_state_hash_reports() -> Vec<(u32, u64)> {
+    STATE_HASH_REPORTS.with(|reports| std::mem::take(&mut *reports.borrow_mut()))
+}
+
+/// Returns and clears every player and tick passed to the host by `check_state_hash` so far on this thread.
+#[cfg(test)]
+pub fn take_state_hash_mismatches() -> Vec<(u32, u32)> {
+    STATE_HASH_MISMATCHES.with(|mismatches| std::mem::take(&mut *mismatches.borrow_mut()))
+}
+
+/// Returns and clears every image requested from the host so far on this thread, in request order.
+#[cfg(test)]
+pub fn take_image_requests() -> Vec<(ImageHandle, String)> {
+    PENDING_IMAGES.with(|pending| std::mem::take(&mut *pending.borrow_mut()))
+}
+
+pub(crate) unsafe fn submit(data: *const u8, data_length: u32) {
+    let bytes = std::slice::from_raw_parts(data, data_length as usize);
+    let commands = DrawList::decode(bytes).unwrap();
+    DRAW_COMMANDS.with(|draw_commands| draw_commands.borrow_mut().extend(commands));
+}
+
+pub(crate) unsafe fn external_log(data: *const u8, data_length: u32) {
+    let bytes = std::slice::from_raw_parts(data, data_length as usize);
+    let message = String::from_utf8_lossy(bytes).into_owned();
+    println!("{}", message);
+    LOG.with(|log| log.borrow_mut().push(message));
+}
+
+pub(crate) unsafe fn load_image(image: u32, path: *const u8, path_length: u32) {
+    let bytes = std::slice::from_raw_parts(path, path_length as usize);
+    let path = String::from_utf8_lossy(bytes).into_owned();
+    PENDING_IMAGES.with(|pending| pending.borrow_mut().push((ImageHandle(image), path)));
+}
+
+pub(crate) unsafe fn external_state_hash(tick: u32, hash_high: u32, hash_low: u32) {
+    let hash = (hash_high as u64) << 32 | hash_low as u64;
+    STATE_HASH_REPORTS.with(|reports| reports.borrow_mut().push((tick, hash)));
+}
+
+pub(crate) unsafe fn external_state_hash_mismatch(player: u32, tick: u32) {
+    STATE_HASH_MISMATCHES.with(|mismatches| mismatches.borrow_mut().push((player, tick)));
+}
+
+/// There is no clock outside the browser, so `Harness::alpha` is drawn with instead.
+pub(crate) unsafe fn external_fixed_update(_tick: u32) {}
+
+/// Where the website's files live. Image paths are relative to this.
+#[cfg(test)]
+pub fn dist_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../dist")
+}
+
+/// There are no fonts outside the browser, so this estimates every character as half the font size wide.
+pub(crate) unsafe fn measure_text(
+    _family: *const u8,
+    _family_length: u32,
+    size: f32,
+    text: *const u8,
+    text_length: u32,
+) -> f32 {
+    let bytes = std::slice::from_raw_parts(text, text_length as usize);
+    String::from_utf8_lossy(bytes).chars().count() as f32 * size * 0.5
+}
+
+/// Owns a `Game` and feeds it events the way the host would.
+///
+/// `mini_engine` keeps its state per thread, so only one `Harness` should be used per thread.
+#[cfg(test)]
+pub struct Harness<G: Game> {
+    pub game: G,
+    /// The player `draw` and `render` draw for.
+    pub local_player: u32,
+    /// The interpolation alpha `draw` and `render` draw with. Defaults to 1, which draws the latest state.
+    pub alpha: f32,
+}
+
+/// The session seed `Harness::new` builds the game with.
+#[cfg(test)]
+pub const DEFAULT_SESSION_SEED: u64 = 0;
+
+#[cfg(test)]
+impl<G: Game> Harness<G> {
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SESSION_SEED)
+    }
+
+    /// Builds the game the way the host does, by sending the session seed first.
+    pub fn with_seed(seed: u64) -> Self {
+        take_draw_commands();
+        take_log();
+        PENDING_IMAGES.with(|pending| pending.borrow_mut().clear());
+        IMAGES.with(|images| images.borrow_mut().clear());
+
+        let mut game = None;
+        dispatch_or_init(&mut game, Event::SetSessionSeed { seed });
+        Self {
+            game: game.unwrap(),
+            local_player: 0,
+            alpha: 1.0,
+        }
+    }
+
+    pub fn send(&mut self, event: Event) {
+        dispatch(&mut self.game, event);
+    }
+
+    /// The hash of the current state, from `mini_engine::state_hash`.
+    pub fn state_hash(&self) -> u64 {
+        state_hash(&self.game)
+    }
+
+    pub fn fixed_updates(&mut self, count: usize) {
+        for _ in 0..count {
+            self.send(Event::FixedUpdate);
+        }
+    }
+
+    /// Loads every image requested so far and sends an `ImageLoaded` event for each.
+    /// Like the host, images that are requested again after loading are skipped.
+    ///
+    /// Panics if an image is missing or is not a PNG.
+    pub fn load_images(&mut self) {
+        for (image, path) in take_image_requests() {
+            if IMAGES.with(|images| images.borrow().contains_key(&image)) {
+                continue;
+            }
+            let (width, height, pixels) = load_png(dist_path().join(&path))
+                .unwrap_or_else(|error| panic!("Could not load image {}: {}", path, error));
+            let canvas = Canvas::from_rgba8(width, height, &pixels);
+            IMAGES.with(|images| images.borrow_mut().insert(image, Rc::new(canvas)));
+            self.send(Event::ImageLoaded {
+                image,
+                width,
+                height,
+            });
+        }
+    }
+
+    /// Sends a `Draw` event and returns the commands it produced.
+    pub fn draw(&mut self) -> Vec<DrawCommand> {
+        take_draw_commands();
+        self.send(Event::Draw {
+            player: self.local_player,
+            alpha: self.alpha,
+        });
+        take_draw_commands()
+    }
+
+    /// Sends a `Draw` event and rasterizes the result onto a transparent canvas.
+    ///
+    /// If the local player's viewport is not `width` by `height`, a `Resize` event is sent first.
+    pub fn render(&mut self, width: u32, height: u32) -> Canvas {
+        let viewport = Viewport::new(width as f32, height as f32, 1.0);
+        if super::viewport(self.local_player) != Some(viewport) {
+            self.send(Event::Resize {
+                player: self.local_player,
+                width: viewport.width,
+                height: viewport.height,
+                device_pixel_ratio: viewport.device_pixel_ratio,
+            });
+        }
+
+        let mut canvas = Canvas::new(width, height);
+        IMAGES.with(|images| {
+            for (&image, pixels) in images.borrow().iter() {
+                canvas.set_image(image, pixels.clone());
+            }
+        });
+        canvas.execute(&self.draw());
+        canvas
+    }
+}
+
+#[cfg(test)]
+impl<G: Game> Default for Harness<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
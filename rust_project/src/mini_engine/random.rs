@@ -0,0 +1,179 @@
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+
+/// Which of the session's random number generators to draw from.
+///
+/// Each stream produces its own sequence, so adding or removing cosmetic randomness
+/// does not change what the simulation stream produces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RandomStream {
+    /// For anything that affects the simulation, such as what gets spawned and where.
+    Simulation,
+    /// For anything that only changes how things look, such as colors.
+    Cosmetic,
+}
+
+impl RandomStream {
+    const ALL: [RandomStream; 2] = [RandomStream::Simulation, RandomStream::Cosmetic];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+
+/// A PCG32 (XSH RR) generator. Its output depends only on its seed and stream,
+/// so every peer gets the same numbers on every platform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Random {
+    state: u64,
+    increment: u64,
+}
+
+impl Random {
+    pub const fn new(seed: u64, stream: u64) -> Self {
+        let mut random = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        random.state = random
+            .state
+            .wrapping_mul(MULTIPLIER)
+            .wrapping_add(random.increment);
+        random.state = random.state.wrapping_add(seed);
+        random.state = random
+            .state
+            .wrapping_mul(MULTIPLIER)
+            .wrapping_add(random.increment);
+        random
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.state = state.wrapping_mul(MULTIPLIER).wrapping_add(self.increment);
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        xorshifted.rotate_right((state >> 59) as u32)
+    }
+
+    /// Returns a number in `range`, with every number equally likely.
+    pub fn range(&mut self, range: Range<u32>) -> u32 {
+        assert!(range.start < range.end, "Empty random range: {:?}", range);
+        let span = range.end - range.start;
+        // Rejects the few results that would make some numbers more likely than others.
+        let threshold = span.wrapping_neg() % span;
+        loop {
+            let product = self.next_u32() as u64 * span as u64;
+            if product as u32 >= threshold {
+                return range.start + (product >> 32) as u32;
+            }
+        }
+    }
+
+    /// Returns a number from 0 up to but not including 1.
+    pub fn f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// The generator's internal state and increment, for hashing.
+    pub fn state(&self) -> (u64, u64) {
+        (self.state, self.increment)
+    }
+}
+
+thread_local! {
+    static SESSION_SEED: Cell<Option<u64>> = const { Cell::new(None) };
+    static STREAMS: RefCell<[Random; 2]> = const {
+        RefCell::new([Random::new(0, 0), Random::new(0, 1)])
+    };
+}
+
+/// Seeds every stream. Only the first seed of a session is used, because every peer
+/// sends one when it connects.
+pub(crate) fn set_session_seed(seed: u64) {
+    if SESSION_SEED.with(|s| s.get()).is_some() {
+        return;
+    }
+    SESSION_SEED.with(|s| s.set(Some(seed)));
+    STREAMS.with(|streams| {
+        let mut streams = streams.borrow_mut();
+        for stream in RandomStream::ALL {
+            streams[stream.index()] = Random::new(seed, stream.index() as u64);
+        }
+    });
+}
+
+/// The seed the host sent for this session, or `None` before it arrives.
+pub fn session_seed() -> Option<u64> {
+    SESSION_SEED.with(|s| s.get())
+}
+
+/// Calls `f` with one of the session's generators.
+///
+/// Only draw from the generators in response to events, so every peer draws the same numbers.
+pub fn with_random<R>(stream: RandomStream, f: impl FnOnce(&mut Random) -> R) -> R {
+    STREAMS.with(|streams| f(&mut streams.borrow_mut()[stream.index()]))
+}
+
+pub fn random_range(stream: RandomStream, range: Range<u32>) -> u32 {
+    with_random(stream, |random| random.range(range))
+}
+
+pub fn random_f32(stream: RandomStream) -> f32 {
+    with_random(stream, |random| random.f32())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_engine::{dispatch_or_init, Event, Game};
+
+    /// Remembers the first number it drew while being built.
+    struct FirstDraw(u32);
+
+    impl Game for FirstDraw {
+        fn init() -> Self {
+            Self(random_range(RandomStream::Simulation, 0..1000))
+        }
+    }
+
+    #[test]
+    fn streams_are_seeded_once_per_session() {
+        set_session_seed(42);
+        let simulation: Vec<u32> = (0..4)
+            .map(|_| random_range(RandomStream::Simulation, 0..1000))
+            .collect();
+        let cosmetic: Vec<u32> = (0..4)
+            .map(|_| random_range(RandomStream::Cosmetic, 0..1000))
+            .collect();
+
+        let mut expected = Random::new(42, 0);
+        let expected: Vec<u32> = (0..4).map(|_| expected.range(0..1000)).collect();
+        assert_eq!(simulation, expected);
+        assert_ne!(simulation, cosmetic);
+
+        // A later peer's seed doesn't restart the streams.
+        set_session_seed(7);
+        assert_eq!(session_seed(), Some(42));
+        assert!((0.0..1.0).contains(&random_f32(RandomStream::Simulation)));
+    }
+
+    #[test]
+    fn game_is_built_from_the_session_seed() {
+        let mut game = None;
+        dispatch_or_init::<FirstDraw>(&mut game, Event::FixedUpdate);
+        assert!(game.is_none());
+
+        dispatch_or_init(&mut game, Event::SetSessionSeed { seed: 42 });
+        let mut expected = Random::new(42, 0);
+        assert_eq!(game.unwrap().0, expected.range(0..1000));
+    }
+
+    #[test]
+    fn matches_reference_output() {
+        // The first outputs of the PCG32 reference implementation for seed 42, stream 54.
+        let mut random = Random::new(42, 54);
+        let outputs: Vec<u32> = (0..3).map(|_| random.next_u32()).collect();
+        assert_eq!(outputs, [0xa15c02b7, 0x7b47f409, 0xba1d3330]);
+    }
+}
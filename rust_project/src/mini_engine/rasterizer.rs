@@ -0,0 +1,1145 @@
+//! A small CPU rasterizer for the commands recorded by the native backend.
+//!
+//! It follows the HTML canvas semantics the browser host relies on: path points are transformed
+//! when they are added, fills use the non-zero winding rule, and colors are blended source-over.
+//! Line widths and dashes are scaled by the transform's average scale, so strokes under a
+//! non-uniform scale are only approximate.
+//!
+//! There are no fonts outside the browser, so text commands are ignored. Images are sampled
+//! with nearest-neighbor filtering and their edges are not antialiased.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::rc::Rc;
+
+use super::draw_list::{LineCap, LineJoin};
+use super::{DrawCommand, ImageHandle, Rect, Transform};
+
+/// Samples per pixel row. Coverage along a row is computed exactly, so this only affects vertical anti-aliasing.
+const VERTICAL_SAMPLES: usize = 4;
+
+/// How far in pixels a flattened curve may stray from the true curve.
+const FLATTEN_TOLERANCE: f32 = 0.1;
+
+/// The canvas default. Sharper miter joins than this are drawn as bevels.
+const MITER_LIMIT: f32 = 10.0;
+
+type Point = [f32; 2];
+
+#[derive(Clone, Debug, Default)]
+struct Subpath {
+    /// Already in pixel coordinates.
+    points: Vec<Point>,
+    closed: bool,
+}
+
+impl Subpath {
+    fn new(points: Vec<Point>, closed: bool) -> Self {
+        Self { points, closed }
+    }
+}
+
+/// An RGBA image that `DrawCommand`s can be rendered into.
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    /// Premultiplied RGBA, each channel from 0.0 to 1.0.
+    pixels: Vec<[f32; 4]>,
+    color: [f32; 4],
+    transform: Transform,
+    path: Vec<Subpath>,
+    line_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    line_dash: Vec<f32>,
+    line_dash_offset: f32,
+    images: BTreeMap<ImageHandle, Rc<Canvas>>,
+}
+
+impl Canvas {
+    /// Creates a fully transparent canvas.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0.0; 4]; (width * height) as usize],
+            color: [0.0, 0.0, 0.0, 1.0],
+            transform: Transform::IDENTITY,
+            path: Vec::new(),
+            line_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            line_dash: Vec::new(),
+            line_dash_offset: 0.0,
+            images: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a canvas from non-premultiplied 8-bit RGBA pixels, row by row.
+    pub fn from_rgba8(width: u32, height: u32, bytes: &[u8]) -> Self {
+        let mut canvas = Self::new(width, height);
+        for (pixel, bytes) in canvas.pixels.iter_mut().zip(bytes.chunks_exact(4)) {
+            let alpha = bytes[3] as f32 / 255.0;
+            for (channel, &byte) in pixel[..3].iter_mut().zip(&bytes[..3]) {
+                *channel = byte as f32 / 255.0 * alpha;
+            }
+            pixel[3] = alpha;
+        }
+        canvas
+    }
+
+    /// Makes `pixels` available to `DrawImage` commands that use `image`.
+    pub fn set_image(&mut self, image: ImageHandle, pixels: Rc<Canvas>) {
+        self.images.insert(image, pixels);
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn execute(&mut self, commands: &[DrawCommand]) {
+        for command in commands {
+            self.execute_command(command);
+        }
+    }
+
+    fn execute_command(&mut self, command: &DrawCommand) {
+        match *command {
+            DrawCommand::SetColor { r, g, b, a } => {
+                self.color = [
+                    r as f32 / 255.0,
+                    g as f32 / 255.0,
+                    b as f32 / 255.0,
+                    a as f32 / 255.0,
+                ];
+            }
+            DrawCommand::DrawCircle { x, y, radius } => {
+                // Matches the host, which begins a new path for each circle.
+                self.path.clear();
+                let points = self.circle(
+                    self.transform.apply(x, y),
+                    radius * self.transform.average_scale(),
+                );
+                self.path.push(Subpath::new(points, true));
+                self.fill();
+            }
+            DrawCommand::DrawRect {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                self.path.clear();
+                let points = vec![
+                    self.transform.apply(x, y),
+                    self.transform.apply(x + width, y),
+                    self.transform.apply(x + width, y + height),
+                    self.transform.apply(x, y + height),
+                ];
+                self.path.push(Subpath::new(points, true));
+                self.fill();
+            }
+            DrawCommand::SetTransform { a, b, c, d, e, f } => {
+                self.transform = Transform { a, b, c, d, e, f };
+            }
+            DrawCommand::BeginPath => self.path.clear(),
+            DrawCommand::MoveTo { x, y } => {
+                let p = self.transform.apply(x, y);
+                self.path.push(Subpath::new(vec![p], false));
+            }
+            DrawCommand::LineTo { x, y } => {
+                let p = self.transform.apply(x, y);
+                // Like the canvas API a `line_to` without a current point acts as a `move_to`.
+                match self.path.last_mut() {
+                    Some(subpath) => subpath.points.push(p),
+                    None => self.path.push(Subpath::new(vec![p], false)),
+                }
+            }
+            DrawCommand::Fill => self.fill(),
+            DrawCommand::Stroke => self.stroke(),
+            DrawCommand::SetLineWidth { width } => {
+                // The canvas ignores widths that are not positive.
+                if width > 0.0 && width.is_finite() {
+                    self.line_width = width;
+                }
+            }
+            DrawCommand::SetLineCap { cap } => self.line_cap = cap,
+            DrawCommand::SetLineJoin { join } => self.line_join = join,
+            DrawCommand::SetLineDash {
+                offset,
+                ref segments,
+            } => {
+                // Like `setLineDash`, invalid patterns are ignored and odd ones are repeated.
+                if segments.iter().all(|s| s.is_finite() && *s >= 0.0) {
+                    self.line_dash = segments.clone();
+                    if self.line_dash.len() % 2 == 1 {
+                        self.line_dash.extend_from_slice(segments);
+                    }
+                }
+                self.line_dash_offset = offset;
+            }
+            DrawCommand::QuadraticTo {
+                control_x,
+                control_y,
+                x,
+                y,
+            } => {
+                let control = self.transform.apply(control_x, control_y);
+                let end = self.transform.apply(x, y);
+                let start = self.ensure_subpath(control);
+                let dd = add(sub(start, scale(control, 2.0)), end);
+                let segments = curve_segments(length(dd) / 4.0);
+                for i in 1..=segments {
+                    let t = i as f32 / segments as f32;
+                    let u = 1.0 - t;
+                    let p = add(
+                        add(scale(start, u * u), scale(control, 2.0 * u * t)),
+                        scale(end, t * t),
+                    );
+                    self.push_point(p);
+                }
+            }
+            DrawCommand::BezierTo {
+                control_0_x,
+                control_0_y,
+                control_1_x,
+                control_1_y,
+                x,
+                y,
+            } => {
+                let control_0 = self.transform.apply(control_0_x, control_0_y);
+                let control_1 = self.transform.apply(control_1_x, control_1_y);
+                let end = self.transform.apply(x, y);
+                let start = self.ensure_subpath(control_0);
+                let dd0 = add(sub(start, scale(control_0, 2.0)), control_1);
+                let dd1 = add(sub(control_0, scale(control_1, 2.0)), end);
+                let segments = curve_segments(length(dd0).max(length(dd1)) * 0.75);
+                for i in 1..=segments {
+                    let t = i as f32 / segments as f32;
+                    let u = 1.0 - t;
+                    let p = add(
+                        add(scale(start, u * u * u), scale(control_0, 3.0 * u * u * t)),
+                        add(scale(control_1, 3.0 * u * t * t), scale(end, t * t * t)),
+                    );
+                    self.push_point(p);
+                }
+            }
+            DrawCommand::Arc {
+                x,
+                y,
+                radius,
+                start_angle,
+                end_angle,
+                counterclockwise,
+            } => {
+                let tau = std::f32::consts::TAU;
+                let sweep = if counterclockwise {
+                    if start_angle - end_angle >= tau {
+                        -tau
+                    } else {
+                        -(start_angle - end_angle).rem_euclid(tau)
+                    }
+                } else if end_angle - start_angle >= tau {
+                    tau
+                } else {
+                    (end_angle - start_angle).rem_euclid(tau)
+                };
+                self.arc([x, y], radius, radius, 0.0, start_angle, sweep, true);
+            }
+            DrawCommand::ArcTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                radius,
+            } => self.arc_to([x1, y1], [x2, y2], radius),
+            DrawCommand::ClosePath => {
+                if let Some(subpath) = self.path.last_mut() {
+                    if let Some(&first) = subpath.points.first() {
+                        subpath.closed = true;
+                        // Like the canvas API the next subpath starts where this one did.
+                        self.path.push(Subpath::new(vec![first], false));
+                    }
+                }
+            }
+            DrawCommand::SetFont { .. }
+            | DrawCommand::SetTextAlign { .. }
+            | DrawCommand::SetTextBaseline { .. }
+            | DrawCommand::DrawText { .. } => {}
+            DrawCommand::DrawImage {
+                image,
+                source,
+                destination,
+            } => {
+                if let Some(pixels) = self.images.get(&image).cloned() {
+                    self.draw_image(&pixels, source, destination);
+                }
+            }
+            DrawCommand::DrawEllipse {
+                x,
+                y,
+                radius_x,
+                radius_y,
+                rotation,
+            } => {
+                self.path.clear();
+                let tau = std::f32::consts::TAU;
+                self.arc([x, y], radius_x, radius_y, rotation, 0.0, tau, false);
+                if let Some(subpath) = self.path.last_mut() {
+                    subpath.closed = true;
+                }
+                self.fill();
+            }
+        }
+    }
+
+    /// Returns the current point, first starting a subpath at `point` if there is none.
+    fn ensure_subpath(&mut self, point: Point) -> Point {
+        match self.path.last().and_then(|subpath| subpath.points.last()) {
+            Some(&current) => current,
+            None => {
+                self.path.push(Subpath::new(vec![point], false));
+                point
+            }
+        }
+    }
+
+    fn push_point(&mut self, point: Point) {
+        match self.path.last_mut() {
+            Some(subpath) => subpath.points.push(point),
+            None => self.path.push(Subpath::new(vec![point], false)),
+        }
+    }
+
+    /// Adds an elliptical arc to the current subpath, connected to the current point by a line.
+    /// Points are computed before transforming so non-uniform transforms stay exact.
+    #[allow(clippy::too_many_arguments)]
+    fn arc(
+        &mut self,
+        center: Point,
+        radius_x: f32,
+        radius_y: f32,
+        rotation: f32,
+        start_angle: f32,
+        sweep: f32,
+        connect: bool,
+    ) {
+        let radius = radius_x.abs().max(radius_y.abs()) * self.transform.average_scale();
+        let full_segments = segments_for_radius(radius) as f32;
+        let segments =
+            ((full_segments * sweep.abs() / std::f32::consts::TAU).ceil() as usize).max(1);
+        let (rotation_sin, rotation_cos) = rotation.sin_cos();
+        for i in 0..=segments {
+            let angle = start_angle + sweep * i as f32 / segments as f32;
+            let (sin, cos) = angle.sin_cos();
+            let (x, y) = (cos * radius_x, sin * radius_y);
+            let p = self.transform.apply(
+                center[0] + x * rotation_cos - y * rotation_sin,
+                center[1] + x * rotation_sin + y * rotation_cos,
+            );
+            if i == 0 && !connect {
+                self.path.push(Subpath::new(vec![p], false));
+            } else {
+                self.push_point(p);
+            }
+        }
+    }
+
+    fn arc_to(&mut self, p1: Point, p2: Point, radius: f32) {
+        let Some(&current) = self.path.last().and_then(|subpath| subpath.points.last()) else {
+            let p1 = self.transform.apply(p1[0], p1[1]);
+            self.path.push(Subpath::new(vec![p1], false));
+            return;
+        };
+        // The arc is built in the untransformed space the arguments are in.
+        let Some(inverse) = self.transform.inverse() else {
+            return;
+        };
+        let p0 = inverse.apply(current[0], current[1]);
+
+        let to_p0 = sub(p0, p1);
+        let to_p2 = sub(p2, p1);
+        if radius <= 0.0
+            || length(to_p0) == 0.0
+            || length(to_p2) == 0.0
+            || cross(to_p0, to_p2) == 0.0
+        {
+            let p1 = self.transform.apply(p1[0], p1[1]);
+            self.push_point(p1);
+            return;
+        }
+
+        let v0 = scale(to_p0, 1.0 / length(to_p0));
+        let v2 = scale(to_p2, 1.0 / length(to_p2));
+        let half_angle = dot(v0, v2).clamp(-1.0, 1.0).acos() / 2.0;
+        let tangent_distance = radius / half_angle.tan();
+        let bisector = add(v0, v2);
+        let center = add(
+            p1,
+            scale(bisector, radius / half_angle.sin() / length(bisector)),
+        );
+        let tangent_0 = add(p1, scale(v0, tangent_distance));
+        let tangent_2 = add(p1, scale(v2, tangent_distance));
+
+        let start_angle = (tangent_0[1] - center[1]).atan2(tangent_0[0] - center[0]);
+        let end_angle = (tangent_2[1] - center[1]).atan2(tangent_2[0] - center[0]);
+        // Take the short way around.
+        let pi = std::f32::consts::PI;
+        let sweep = (end_angle - start_angle + pi).rem_euclid(std::f32::consts::TAU) - pi;
+        self.arc(center, radius, radius, 0.0, start_angle, sweep, true);
+    }
+
+    /// A circle flattened into a polygon, all in pixel coordinates.
+    fn circle(&self, center: Point, radius: f32) -> Vec<Point> {
+        let segments = segments_for_radius(radius);
+        (0..segments)
+            .map(|i| {
+                let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+                let (sin, cos) = angle.sin_cos();
+                [center[0] + cos * radius, center[1] + sin * radius]
+            })
+            .collect()
+    }
+
+    fn fill(&mut self) {
+        let polygons: Vec<Vec<Point>> = self.path.iter().map(|s| s.points.clone()).collect();
+        self.fill_polygons(&polygons);
+    }
+
+    /// Strokes the current path by building polygons that cover the stroke and filling them.
+    fn stroke(&mut self) {
+        let scale = self.transform.average_scale();
+        let half_width = self.line_width * scale / 2.0;
+        let dash: Vec<f32> = self.line_dash.iter().map(|d| d * scale).collect();
+        let dashed = dash.iter().sum::<f32>() > 0.0;
+
+        let mut polygons = Vec::new();
+        for subpath in &self.path {
+            let mut points = subpath.points.clone();
+            points.dedup();
+            if subpath.closed && points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            if points.len() < 2 {
+                continue;
+            }
+
+            if dashed {
+                if subpath.closed {
+                    points.push(points[0]);
+                }
+                for piece in dash_polyline(&points, &dash, self.line_dash_offset * scale) {
+                    self.stroke_polyline(&piece, false, half_width, &mut polygons);
+                }
+            } else {
+                self.stroke_polyline(&points, subpath.closed, half_width, &mut polygons);
+            }
+        }
+
+        // Overlapping polygons only union correctly under non-zero winding if they all wind the same way.
+        for polygon in &mut polygons {
+            if signed_area(polygon) < 0.0 {
+                polygon.reverse();
+            }
+        }
+        self.fill_polygons(&polygons);
+    }
+
+    fn stroke_polyline(
+        &self,
+        points: &[Point],
+        closed: bool,
+        half_width: f32,
+        polygons: &mut Vec<Vec<Point>>,
+    ) {
+        let mut points = points.to_vec();
+        points.dedup();
+        if points.len() < 2 {
+            return;
+        }
+        if closed {
+            points.push(points[0]);
+        }
+
+        for segment in points.windows(2) {
+            let normal = scale(normal(segment[0], segment[1]), half_width);
+            polygons.push(vec![
+                add(segment[0], normal),
+                add(segment[1], normal),
+                sub(segment[1], normal),
+                sub(segment[0], normal),
+            ]);
+        }
+
+        // Joins between each pair of segments, including where a closed path meets itself.
+        let joins = if closed {
+            1..points.len()
+        } else {
+            1..points.len() - 1
+        };
+        for i in joins {
+            let previous = points[i - 1];
+            let point = points[i];
+            let next = if i + 1 < points.len() {
+                points[i + 1]
+            } else {
+                points[1]
+            };
+            self.join(previous, point, next, half_width, polygons);
+        }
+
+        if !closed {
+            let end = points.len() - 1;
+            self.cap(points[0], points[1], half_width, polygons);
+            self.cap(points[end], points[end - 1], half_width, polygons);
+        }
+    }
+
+    fn join(
+        &self,
+        previous: Point,
+        point: Point,
+        next: Point,
+        half_width: f32,
+        polygons: &mut Vec<Vec<Point>>,
+    ) {
+        if self.line_join == LineJoin::Round {
+            polygons.push(self.circle(point, half_width));
+            return;
+        }
+
+        let n0 = normal(previous, point);
+        let n1 = normal(point, next);
+        let d0 = direction(previous, point);
+        let d1 = direction(point, next);
+        // The outside of the turn is the side the normals point away from.
+        let side = if cross(d0, d1) > 0.0 { -1.0 } else { 1.0 };
+        let outer0 = add(point, scale(n0, half_width * side));
+        let outer1 = add(point, scale(n1, half_width * side));
+
+        let cos = dot(d0, d1);
+        let miter_ratio = (2.0 / (1.0 + cos)).sqrt();
+        if self.line_join == LineJoin::Miter && cos > -1.0 && miter_ratio <= MITER_LIMIT {
+            let bisector = scale(add(n0, n1), half_width * side / (1.0 + cos));
+            polygons.push(vec![point, outer0, add(point, bisector), outer1]);
+        } else {
+            polygons.push(vec![point, outer0, outer1]);
+        }
+    }
+
+    /// Adds a cap to the end of a line at `point` that continues towards `towards`.
+    fn cap(&self, point: Point, towards: Point, half_width: f32, polygons: &mut Vec<Vec<Point>>) {
+        match self.line_cap {
+            LineCap::Butt => {}
+            LineCap::Round => polygons.push(self.circle(point, half_width)),
+            LineCap::Square => {
+                let normal = scale(normal(point, towards), half_width);
+                let back = sub(point, scale(direction(point, towards), half_width));
+                polygons.push(vec![
+                    add(point, normal),
+                    add(back, normal),
+                    sub(back, normal),
+                    sub(point, normal),
+                ]);
+            }
+        }
+    }
+
+    /// Fills polygons, already in pixel coordinates, with the non-zero winding rule.
+    fn fill_polygons(&mut self, polygons: &[Vec<Point>]) {
+        // Each edge is (x0, y0, x1, y1, winding) with y0 < y1. Polygons are implicitly closed.
+        let mut edges = Vec::new();
+        for polygon in polygons {
+            for (i, p0) in polygon.iter().enumerate() {
+                let p1 = polygon[(i + 1) % polygon.len()];
+                if p0[1] < p1[1] {
+                    edges.push((p0[0], p0[1], p1[0], p1[1], 1));
+                } else if p0[1] > p1[1] {
+                    edges.push((p1[0], p1[1], p0[0], p0[1], -1));
+                }
+            }
+        }
+        if edges.is_empty() {
+            return;
+        }
+
+        let min_y = edges.iter().map(|e| e.1).fold(f32::INFINITY, f32::min);
+        let max_y = edges.iter().map(|e| e.3).fold(f32::NEG_INFINITY, f32::max);
+        let start_row = (min_y.floor().max(0.0) as u32).min(self.height);
+        let end_row = (max_y.ceil().max(0.0) as u32).min(self.height);
+
+        let mut coverage = vec![0.0f32; self.width as usize];
+        let mut crossings = Vec::new();
+        for row in start_row..end_row {
+            coverage.iter_mut().for_each(|c| *c = 0.0);
+            for sample in 0..VERTICAL_SAMPLES {
+                let y = row as f32 + (sample as f32 + 0.5) / VERTICAL_SAMPLES as f32;
+
+                crossings.clear();
+                for &(x0, y0, x1, y1, winding) in &edges {
+                    if y >= y0 && y < y1 {
+                        crossings.push((x0 + (y - y0) / (y1 - y0) * (x1 - x0), winding));
+                    }
+                }
+                crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                let mut winding = 0;
+                for pair in crossings.windows(2) {
+                    winding += pair[0].1;
+                    if winding != 0 {
+                        add_span(
+                            &mut coverage,
+                            pair[0].0,
+                            pair[1].0,
+                            1.0 / VERTICAL_SAMPLES as f32,
+                        );
+                    }
+                }
+            }
+
+            for (x, &c) in coverage.iter().enumerate() {
+                if c > 0.0 {
+                    self.blend(x as u32, row, c.min(1.0));
+                }
+            }
+        }
+    }
+
+    /// Draws the `source` part of `image` stretched over `destination` by mapping the center of
+    /// every pixel the destination may cover back into the image.
+    fn draw_image(&mut self, image: &Canvas, source: Rect, destination: Rect) {
+        if destination.width == 0.0 || destination.height == 0.0 {
+            return;
+        }
+        let Some(inverse) = self.transform.inverse() else {
+            return;
+        };
+
+        let corners = [
+            [destination.x, destination.y],
+            [destination.x + destination.width, destination.y],
+            [destination.x, destination.y + destination.height],
+            [
+                destination.x + destination.width,
+                destination.y + destination.height,
+            ],
+        ]
+        .map(|[x, y]| self.transform.apply(x, y));
+        let min = |axis: usize| {
+            corners
+                .iter()
+                .map(|c| c[axis])
+                .fold(f32::INFINITY, f32::min)
+        };
+        let max = |axis: usize| {
+            corners
+                .iter()
+                .map(|c| c[axis])
+                .fold(f32::NEG_INFINITY, f32::max)
+        };
+        let start_x = (min(0).floor().max(0.0) as u32).min(self.width);
+        let end_x = (max(0).ceil().max(0.0) as u32).min(self.width);
+        let start_y = (min(1).floor().max(0.0) as u32).min(self.height);
+        let end_y = (max(1).ceil().max(0.0) as u32).min(self.height);
+
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                let [u, v] = inverse.apply(x as f32 + 0.5, y as f32 + 0.5);
+                let u = (u - destination.x) / destination.width;
+                let v = (v - destination.y) / destination.height;
+                if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+                    continue;
+                }
+
+                let source_x = (source.x + u * source.width).floor();
+                let source_y = (source.y + v * source.height).floor();
+                if source_x < 0.0
+                    || source_y < 0.0
+                    || source_x >= image.width as f32
+                    || source_y >= image.height as f32
+                {
+                    continue;
+                }
+                let color =
+                    image.pixels[(source_y as u32 * image.width + source_x as u32) as usize];
+                self.blend_premultiplied(x, y, color);
+            }
+        }
+    }
+
+    fn blend_premultiplied(&mut self, x: u32, y: u32, color: [f32; 4]) {
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        let alpha = color[3];
+        for (channel, color) in pixel.iter_mut().zip(color) {
+            *channel = color + *channel * (1.0 - alpha);
+        }
+    }
+
+    fn blend(&mut self, x: u32, y: u32, coverage: f32) {
+        let alpha = self.color[3] * coverage;
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        for (channel, color) in pixel[..3].iter_mut().zip(&self.color[..3]) {
+            *channel = color * alpha + *channel * (1.0 - alpha);
+        }
+        pixel[3] = alpha + pixel[3] * (1.0 - alpha);
+    }
+
+    /// Returns the pixels as non-premultiplied 8-bit RGBA, row by row.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in &self.pixels {
+            let alpha = pixel[3];
+            for channel in &pixel[..3] {
+                let value = if alpha > 0.0 { channel / alpha } else { 0.0 };
+                bytes.push(to_u8(value));
+            }
+            bytes.push(to_u8(alpha));
+        }
+        bytes
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.to_rgba8())
+            .map_err(io::Error::other)
+    }
+
+    /// Compares the canvas to a PNG on disk, allowing each channel to be off by `tolerance`.
+    ///
+    /// If the `UPDATE_GOLDEN` environment variable is set, the canvas is written to `path` instead.
+    /// On a mismatch, or if the golden image is missing, the canvas is written next to it with an
+    /// `.actual.png` extension to make it easy to inspect.
+    pub fn assert_matches_golden(&self, path: impl AsRef<Path>, tolerance: u8) {
+        let path = path.as_ref();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            self.save_png(path).unwrap();
+            println!("Wrote golden image: {}", path.display());
+            return;
+        }
+        if !path.exists() {
+            let actual_path = path.with_extension("actual.png");
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            self.save_png(&actual_path).unwrap();
+            panic!(
+                "Golden image {} is missing. Actual output written to {}. Run with UPDATE_GOLDEN=1 to accept it.",
+                path.display(),
+                actual_path.display()
+            );
+        }
+
+        let (width, height, expected) = load_png(path).unwrap();
+        let actual = self.to_rgba8();
+        let mismatched_pixels = if (width, height) == (self.width, self.height) {
+            expected
+                .chunks(4)
+                .zip(actual.chunks(4))
+                .filter(|(e, a)| {
+                    e.iter()
+                        .zip(a.iter())
+                        .any(|(e, a)| e.abs_diff(*a) > tolerance)
+                })
+                .count()
+        } else {
+            actual.len() / 4
+        };
+
+        if mismatched_pixels > 0 {
+            let actual_path = path.with_extension("actual.png");
+            self.save_png(&actual_path).unwrap();
+            panic!(
+                "{} pixels differ from {} (expected {}x{}, got {}x{}). Actual output written to {}",
+                mismatched_pixels,
+                path.display(),
+                width,
+                height,
+                self.width,
+                self.height,
+                actual_path.display()
+            );
+        }
+    }
+}
+
+/// Loads a PNG as non-premultiplied 8-bit RGBA. Returns the width, height, and pixels.
+pub fn load_png(path: impl AsRef<Path>) -> io::Result<(u32, u32, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::ALPHA);
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut bytes = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut bytes).map_err(io::Error::other)?;
+    bytes.truncate(info.buffer_size());
+    Ok((info.width, info.height, bytes))
+}
+
+fn segments_for_radius(radius: f32) -> usize {
+    let radius = radius.abs();
+    if radius <= FLATTEN_TOLERANCE {
+        return 8;
+    }
+    let angle = (1.0 - FLATTEN_TOLERANCE / radius).acos();
+    ((std::f32::consts::PI / angle).ceil() as usize).clamp(8, 1024)
+}
+
+/// How many line segments a curve needs to stay within `FLATTEN_TOLERANCE`, given a bound on
+/// how far it strays from a single straight segment. Splitting into `n` segments divides that by `n²`.
+fn curve_segments(curvature: f32) -> usize {
+    ((curvature / FLATTEN_TOLERANCE).sqrt().ceil() as usize).clamp(1, 1024)
+}
+
+/// Splits a polyline into the pieces that are "on" in a dash pattern.
+fn dash_polyline(points: &[Point], pattern: &[f32], offset: f32) -> Vec<Vec<Point>> {
+    let total: f32 = pattern.iter().sum();
+    let mut index = 0;
+    let mut phase = offset.rem_euclid(total);
+    while phase >= pattern[index] {
+        phase -= pattern[index];
+        index = (index + 1) % pattern.len();
+    }
+    let mut remaining = pattern[index] - phase;
+    let mut on = index % 2 == 0;
+
+    let mut pieces = Vec::new();
+    let mut current = if on { vec![points[0]] } else { Vec::new() };
+    for segment in points.windows(2) {
+        let mut start = segment[0];
+        let end = segment[1];
+        let mut length = length(sub(end, start));
+        while length > remaining {
+            let split = add(start, scale(sub(end, start), remaining / length));
+            current.push(split);
+            if on {
+                pieces.push(std::mem::take(&mut current));
+            }
+            on = !on;
+            start = split;
+            length -= remaining;
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+        remaining -= length;
+        if on {
+            current.push(end);
+        }
+    }
+    if on && current.len() > 1 {
+        pieces.push(current);
+    }
+    pieces
+}
+
+fn signed_area(polygon: &[Point]) -> f32 {
+    let mut area = 0.0;
+    for (i, p0) in polygon.iter().enumerate() {
+        let p1 = polygon[(i + 1) % polygon.len()];
+        area += p0[0] * p1[1] - p1[0] * p0[1];
+    }
+    area / 2.0
+}
+
+fn add(a: Point, b: Point) -> Point {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(a: Point, s: f32) -> Point {
+    [a[0] * s, a[1] * s]
+}
+
+fn dot(a: Point, b: Point) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn cross(a: Point, b: Point) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn length(a: Point) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn direction(from: Point, to: Point) -> Point {
+    let d = sub(to, from);
+    scale(d, 1.0 / length(d))
+}
+
+/// The unit vector perpendicular to the line from `from` to `to`.
+fn normal(from: Point, to: Point) -> Point {
+    let d = direction(from, to);
+    [-d[1], d[0]]
+}
+
+/// Adds `weight` times how much of each pixel the span from `start` to `end` covers.
+fn add_span(coverage: &mut [f32], start: f32, end: f32, weight: f32) {
+    let start = start.max(0.0);
+    let end = end.min(coverage.len() as f32);
+    if start >= end {
+        return;
+    }
+    let pixels = coverage
+        .iter_mut()
+        .enumerate()
+        .take(end.ceil() as usize)
+        .skip(start.floor() as usize);
+    for (x, c) in pixels {
+        *c += (end.min(x as f32 + 1.0) - start.max(x as f32)) * weight;
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(canvas: &Canvas, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * canvas.width() + x) * 4) as usize;
+        canvas.to_rgba8()[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn rect_respects_transform() {
+        let mut canvas = Canvas::new(20, 20);
+        canvas.execute(&[
+            DrawCommand::SetColor {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+            DrawCommand::SetTransform {
+                a: 2.0,
+                b: 0.0,
+                c: 0.0,
+                d: 2.0,
+                e: 10.0,
+                f: 0.0,
+            },
+            DrawCommand::DrawRect {
+                x: 0.0,
+                y: 0.0,
+                width: 4.0,
+                height: 4.0,
+            },
+        ]);
+
+        assert_eq!(pixel(&canvas, 12, 2), [255, 0, 0, 255]);
+        assert_eq!(pixel(&canvas, 17, 7), [255, 0, 0, 255]);
+        assert_eq!(pixel(&canvas, 9, 2), [0, 0, 0, 0]);
+        assert_eq!(pixel(&canvas, 12, 8), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn colors_blend_source_over() {
+        let rect = DrawCommand::DrawRect {
+            x: 0.0,
+            y: 0.0,
+            width: 4.0,
+            height: 4.0,
+        };
+        let mut canvas = Canvas::new(4, 4);
+        canvas.execute(&[
+            DrawCommand::SetColor {
+                r: 0,
+                g: 0,
+                b: 255,
+                a: 255,
+            },
+            rect.clone(),
+            DrawCommand::SetColor {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 128,
+            },
+            rect,
+        ]);
+
+        assert_eq!(pixel(&canvas, 1, 1), [128, 0, 127, 255]);
+    }
+
+    fn stroke_line(canvas: &mut Canvas, style: &[DrawCommand]) {
+        canvas.execute(style);
+        canvas.execute(&[
+            DrawCommand::SetLineWidth { width: 4.0 },
+            DrawCommand::BeginPath,
+            DrawCommand::MoveTo { x: 4.0, y: 10.0 },
+            DrawCommand::LineTo { x: 16.0, y: 10.0 },
+            DrawCommand::Stroke,
+        ]);
+    }
+
+    #[test]
+    fn stroke_caps() {
+        let mut butt = Canvas::new(20, 20);
+        stroke_line(&mut butt, &[]);
+        assert_eq!(pixel(&butt, 10, 8)[3], 255);
+        assert_eq!(pixel(&butt, 10, 12)[3], 0);
+        assert_eq!(pixel(&butt, 3, 10)[3], 0);
+
+        let mut square = Canvas::new(20, 20);
+        stroke_line(
+            &mut square,
+            &[DrawCommand::SetLineCap {
+                cap: LineCap::Square,
+            }],
+        );
+        assert_eq!(pixel(&square, 3, 10)[3], 255);
+        assert_eq!(pixel(&square, 16, 10)[3], 255);
+        assert_eq!(pixel(&square, 18, 10)[3], 0);
+    }
+
+    #[test]
+    fn full_arc_matches_circle() {
+        let mut circle = Canvas::new(20, 20);
+        circle.execute(&[DrawCommand::DrawCircle {
+            x: 10.0,
+            y: 10.0,
+            radius: 7.0,
+        }]);
+
+        let mut arc = Canvas::new(20, 20);
+        arc.execute(&[
+            DrawCommand::BeginPath,
+            DrawCommand::Arc {
+                x: 10.0,
+                y: 10.0,
+                radius: 7.0,
+                start_angle: 0.0,
+                end_angle: std::f32::consts::TAU,
+                counterclockwise: false,
+            },
+            DrawCommand::Fill,
+        ]);
+
+        assert_eq!(circle.to_rgba8(), arc.to_rgba8());
+    }
+
+    #[test]
+    fn quadratic_curve_fill() {
+        let mut canvas = Canvas::new(20, 20);
+        canvas.execute(&[
+            DrawCommand::BeginPath,
+            DrawCommand::MoveTo { x: 0.0, y: 0.0 },
+            // Peaks at y = 10 halfway along.
+            DrawCommand::QuadraticTo {
+                control_x: 10.0,
+                control_y: 20.0,
+                x: 20.0,
+                y: 0.0,
+            },
+            DrawCommand::ClosePath,
+            DrawCommand::Fill,
+        ]);
+
+        assert_eq!(pixel(&canvas, 10, 8)[3], 255);
+        assert_eq!(pixel(&canvas, 10, 11)[3], 0);
+        assert_eq!(pixel(&canvas, 2, 8)[3], 0);
+    }
+
+    #[test]
+    fn images_are_scaled_and_cropped() {
+        // A 2x2 image: red, green on the top row and blue, transparent on the bottom row.
+        let image = ImageHandle(0);
+        let mut pixels = Canvas::new(2, 2);
+        pixels.execute(&[
+            DrawCommand::SetColor {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+            DrawCommand::DrawRect {
+                x: 0.0,
+                y: 0.0,
+                width: 1.0,
+                height: 1.0,
+            },
+            DrawCommand::SetColor {
+                r: 0,
+                g: 255,
+                b: 0,
+                a: 255,
+            },
+            DrawCommand::DrawRect {
+                x: 1.0,
+                y: 0.0,
+                width: 1.0,
+                height: 1.0,
+            },
+            DrawCommand::SetColor {
+                r: 0,
+                g: 0,
+                b: 255,
+                a: 255,
+            },
+            DrawCommand::DrawRect {
+                x: 0.0,
+                y: 1.0,
+                width: 1.0,
+                height: 1.0,
+            },
+        ]);
+        let pixels = Rc::new(Canvas::from_rgba8(2, 2, &pixels.to_rgba8()));
+
+        let mut canvas = Canvas::new(8, 8);
+        canvas.set_image(image, pixels);
+        canvas.execute(&[
+            DrawCommand::DrawImage {
+                image,
+                source: Rect::new(0.0, 0.0, 2.0, 2.0),
+                destination: Rect::new(0.0, 0.0, 4.0, 4.0),
+            },
+            // Only the green pixel.
+            DrawCommand::DrawImage {
+                image,
+                source: Rect::new(1.0, 0.0, 1.0, 1.0),
+                destination: Rect::new(4.0, 4.0, 4.0, 4.0),
+            },
+        ]);
+
+        assert_eq!(pixel(&canvas, 1, 1), [255, 0, 0, 255]);
+        assert_eq!(pixel(&canvas, 2, 1), [0, 255, 0, 255]);
+        assert_eq!(pixel(&canvas, 1, 2), [0, 0, 255, 255]);
+        assert_eq!(pixel(&canvas, 3, 3), [0, 0, 0, 0]);
+        assert_eq!(pixel(&canvas, 5, 5), [0, 255, 0, 255]);
+        assert_eq!(pixel(&canvas, 7, 7), [0, 255, 0, 255]);
+        assert_eq!(pixel(&canvas, 5, 1), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn stroke_dashes() {
+        let mut canvas = Canvas::new(20, 20);
+        stroke_line(
+            &mut canvas,
+            &[DrawCommand::SetLineDash {
+                offset: 0.0,
+                segments: vec![3.0],
+            }],
+        );
+        assert_eq!(pixel(&canvas, 5, 10)[3], 255);
+        assert_eq!(pixel(&canvas, 8, 10)[3], 0);
+        assert_eq!(pixel(&canvas, 11, 10)[3], 255);
+        assert_eq!(pixel(&canvas, 14, 10)[3], 0);
+    }
+}
@@ -0,0 +1,159 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use super::{input, time, unsafe_external, with_random, Game, RandomStream};
+
+/// How many fixed updates' hashes are kept to compare with other peers' reports.
+pub const STATE_HASH_HISTORY: u64 = 600;
+
+/// A 64 bit FNV-1a hasher that gives the same result on every platform, unlike `DefaultHasher`.
+///
+/// Anything that implements `Hash` can be written to it. Floats don't, so write them with `write_f32`.
+#[derive(Clone, Debug)]
+pub struct StateHasher(u64);
+
+impl StateHasher {
+    pub fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    /// Writes the exact bits, so even a difference in the last digit changes the hash.
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StateHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    // `usize` is 4 bytes on Wasm, so lengths and enum discriminants are widened to match native builds.
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_isize(&mut self, value: isize) {
+        self.write_i64(value as i64);
+    }
+}
+
+/// Hashes the state `mini_engine` keeps for the simulation, followed by the game's from `Game::hash_state`.
+///
+/// Peers that processed the same events should always get the same hash.
+pub fn state_hash(game: &impl Game) -> u64 {
+    let mut hasher = StateHasher::new();
+    let time = time();
+    hasher.write_u64(time.tick);
+    hasher.write_f32(time.fixed_delta);
+    for stream in [RandomStream::Simulation, RandomStream::Cosmetic] {
+        with_random(stream, |random| random.state().hash(&mut hasher));
+    }
+    input::hash_input(&mut hasher);
+    game.hash_state(&mut hasher);
+    hasher.finish()
+}
+
+thread_local! {
+    static STATE_HASHES: RefCell<BTreeMap<u64, u64>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Remembers the hash for the current tick. Called after every fixed update.
+pub(crate) fn record_state_hash(hash: u64) {
+    STATE_HASHES.with(|hashes| {
+        let mut hashes = hashes.borrow_mut();
+        hashes.insert(time().tick, hash);
+        while hashes.len() as u64 > STATE_HASH_HISTORY {
+            hashes.pop_first();
+        }
+    });
+}
+
+/// The hash of the state right after the fixed update that finished `tick`,
+/// or `None` if it hasn't happened yet or is older than `STATE_HASH_HISTORY`.
+pub fn recorded_state_hash(tick: u64) -> Option<u64> {
+    STATE_HASHES.with(|hashes| hashes.borrow().get(&tick).copied())
+}
+
+/// Passes the hash recorded `ticks_ago` fixed updates ago to the host, to send to other peers.
+///
+/// Older hashes are less likely to change because of a late event, so comparing them
+/// avoids reporting a desync that a rollback was about to fix.
+pub fn report_state_hash(ticks_ago: u32) {
+    let Some(tick) = time().tick.checked_sub(ticks_ago as u64) else {
+        return;
+    };
+    if let Some(hash) = recorded_state_hash(tick) {
+        unsafe {
+            unsafe_external::external_state_hash(tick as u32, (hash >> 32) as u32, hash as u32);
+        }
+    }
+}
+
+/// Compares another peer's hash with this peer's, and tells the host if they disagree.
+///
+/// This runs again whenever a rollback replays the report, so the host decides what to log
+/// and remembers which peers it has already logged.
+pub(crate) fn check_state_hash(player: u32, tick: u64, hash: u64) {
+    if recorded_state_hash(tick).is_some_and(|own_hash| own_hash != hash) {
+        unsafe {
+            unsafe_external::external_state_hash_mismatch(player, tick as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_engine::native::{take_state_hash_mismatches, take_state_hash_reports};
+
+    #[test]
+    fn hasher_matches_fnv_1a() {
+        let mut hasher = StateHasher::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn reported_hash_is_split_for_the_host() {
+        record_state_hash(0x0123456789abcdef);
+        let tick = time().tick;
+        take_state_hash_reports();
+
+        report_state_hash(0);
+        assert_eq!(
+            take_state_hash_reports(),
+            [(tick as u32, 0x0123456789abcdef)]
+        );
+        report_state_hash(tick as u32 + 1);
+        assert!(take_state_hash_reports().is_empty());
+    }
+
+    #[test]
+    fn only_mismatches_are_passed_to_the_host() {
+        record_state_hash(5);
+        let tick = time().tick;
+        take_state_hash_mismatches();
+
+        check_state_hash(2, tick, 5);
+        assert!(take_state_hash_mismatches().is_empty());
+        check_state_hash(2, tick, 6);
+        assert_eq!(take_state_hash_mismatches(), [(2, tick as u32)]);
+        // Nothing is known about ticks that haven't happened yet.
+        check_state_hash(2, tick + 1, 6);
+        assert!(take_state_hash_mismatches().is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+use std::cell::RefCell;
+
+use super::draw_list::{record, DrawCommand, TextAlign, TextBaseline};
+
+// Font families bundled with the website. See the `@font-face` rules in `dist/index.html`.
+pub const INTER: &str = "Inter";
+pub const INTER_DISPLAY: &str = "InterDisplay";
+pub const PERMANENT_MARKER: &str = "PermanentMarker";
+
+pub const DEFAULT_FONT_FAMILY: &str = INTER;
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+struct Font {
+    family: String,
+    size: f32,
+}
+
+thread_local! {
+    // Mirrors the host's font so `measure_text` can pass it along. `None` means the default font.
+    static FONT: RefCell<Option<Font>> = const { RefCell::new(None) };
+}
+
+/// Sets the font used by `draw_text` and `measure_text` for the rest of the frame.
+/// `size` is in pixels before the transform is applied.
+///
+/// Every frame starts with `DEFAULT_FONT_FAMILY` at `DEFAULT_FONT_SIZE`.
+pub fn set_font(family: &str, size: f32) {
+    FONT.with(|font| {
+        *font.borrow_mut() = Some(Font {
+            family: family.to_string(),
+            size,
+        })
+    });
+    record(DrawCommand::SetFont {
+        family: family.to_string(),
+        size,
+    });
+}
+
+/// Every frame starts with `TextAlign::Left`.
+pub fn set_text_align(align: TextAlign) {
+    record(DrawCommand::SetTextAlign { align });
+}
+
+/// Every frame starts with `TextBaseline::Alphabetic`.
+pub fn set_text_baseline(baseline: TextBaseline) {
+    record(DrawCommand::SetTextBaseline { baseline });
+}
+
+/// Fills `text` with the current color.
+pub fn draw_text(text: &str, x: f32, y: f32) {
+    record(DrawCommand::DrawText {
+        x,
+        y,
+        text: text.to_string(),
+    });
+}
+
+/// Returns the width `text` would be drawn with in the current font, ignoring the transform.
+///
+/// Font rendering differs between browsers, so the result can differ between players.
+/// Only use it for drawing, never to make decisions that affect the game's state.
+pub fn measure_text(text: &str) -> f32 {
+    FONT.with(|font| {
+        let font = font.borrow();
+        let (family, size) = match font.as_ref() {
+            Some(font) => (font.family.as_str(), font.size),
+            None => (DEFAULT_FONT_FAMILY, DEFAULT_FONT_SIZE),
+        };
+        unsafe {
+            super::unsafe_external::measure_text(
+                family.as_ptr(),
+                family.len() as _,
+                size,
+                text.as_ptr(),
+                text.len() as _,
+            )
+        }
+    })
+}
+
+/// Matches the host, which resets its text state at the start of every draw list.
+pub(crate) fn reset_font() {
+    FONT.with(|font| *font.borrow_mut() = None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mini_engine::native::Harness;
+    use crate::mini_engine::Game;
+
+    /// Sets a font on its first frame only, and measures the same text every frame.
+    #[derive(Default)]
+    struct Label {
+        frames: u32,
+        widths: Vec<f32>,
+    }
+
+    impl Game for Label {
+        fn init() -> Self {
+            Self::default()
+        }
+
+        fn draw(&mut self) {
+            if self.frames == 0 {
+                set_font(PERMANENT_MARKER, 40.0);
+                set_text_align(TextAlign::Center);
+                set_text_baseline(TextBaseline::Middle);
+            }
+            self.widths.push(measure_text("Tangle"));
+            draw_text("Tangle", 10.0, 20.0);
+            self.frames += 1;
+        }
+    }
+
+    #[test]
+    fn text_state_is_recorded() {
+        let mut harness = Harness::<Label>::new();
+        assert_eq!(
+            harness.draw(),
+            [
+                DrawCommand::SetFont {
+                    family: PERMANENT_MARKER.to_string(),
+                    size: 40.0,
+                },
+                DrawCommand::SetTextAlign {
+                    align: TextAlign::Center,
+                },
+                DrawCommand::SetTextBaseline {
+                    baseline: TextBaseline::Middle,
+                },
+                DrawCommand::DrawText {
+                    x: 10.0,
+                    y: 20.0,
+                    text: "Tangle".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn font_is_reset_every_frame() {
+        let mut harness = Harness::<Label>::new();
+        harness.draw();
+        harness.draw();
+        assert_eq!(
+            harness.game.widths,
+            [6.0 * 40.0 * 0.5, 6.0 * DEFAULT_FONT_SIZE * 0.5]
+        );
+    }
+
+    #[test]
+    fn native_measure_text_estimates_half_the_size_per_character() {
+        reset_font();
+        assert_eq!(measure_text("Tangle"), 6.0 * DEFAULT_FONT_SIZE * 0.5);
+        set_font(INTER_DISPLAY, 30.0);
+        assert_eq!(measure_text("Tangle"), 6.0 * 30.0 * 0.5);
+        assert_eq!(measure_text(""), 0.0);
+    }
+}
@@ -0,0 +1,81 @@
+use std::cell::Cell;
+
+/// Matches the host's default fixed update interval.
+pub const DEFAULT_FIXED_DELTA: f32 = 1.0 / 60.0;
+
+/// Simulation time, which only advances with fixed updates so it is the same on every peer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Time {
+    /// How many fixed updates finished before the current one.
+    pub tick: u64,
+    /// Seconds between fixed updates, as configured by the host.
+    pub fixed_delta: f32,
+    /// Seconds simulated before the current fixed update.
+    pub elapsed: f64,
+}
+
+thread_local! {
+    static TIME: Cell<Time> = const {
+        Cell::new(Time {
+            tick: 0,
+            fixed_delta: DEFAULT_FIXED_DELTA,
+            elapsed: 0.0,
+        })
+    };
+    static INTERPOLATION_ALPHA: Cell<f32> = const { Cell::new(1.0) };
+}
+
+pub(crate) fn set_fixed_delta(seconds: f32) {
+    TIME.with(|time| {
+        time.set(Time {
+            fixed_delta: seconds,
+            ..time.get()
+        })
+    });
+}
+
+/// Called after `Game::fixed_update`.
+pub(crate) fn advance_time() {
+    TIME.with(|time| {
+        let current = time.get();
+        time.set(Time {
+            tick: current.tick + 1,
+            elapsed: current.elapsed + current.fixed_delta as f64,
+            ..current
+        })
+    });
+}
+
+pub fn time() -> Time {
+    TIME.with(|time| time.get())
+}
+
+pub(crate) fn set_interpolation_alpha(alpha: f32) {
+    INTERPOLATION_ALPHA.with(|interpolation_alpha| interpolation_alpha.set(alpha.clamp(0.0, 1.0)));
+}
+
+/// Returns how far the frame being drawn is between the last fixed update and the next one, from 0 to 1.
+///
+/// Drawing positions blended from the last two fixed updates by this amount keeps motion smooth when the
+/// display refreshes at a different rate than fixed updates run. Only meaningful during `Game::draw`.
+pub fn interpolation_alpha() -> f32 {
+    INTERPOLATION_ALPHA.with(|interpolation_alpha| interpolation_alpha.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_advances_by_the_configured_delta() {
+        advance_time();
+        set_fixed_delta(0.5);
+        advance_time();
+        advance_time();
+
+        let time = time();
+        assert_eq!(time.tick, 3);
+        assert_eq!(time.fixed_delta, 0.5);
+        assert!((time.elapsed - (DEFAULT_FIXED_DELTA as f64 + 1.0)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,204 @@
+use std::cell::RefCell;
+
+use super::draw_list::{record, DrawCommand};
+
+/// A 2D affine transform laid out like the HTML canvas `setTransform` arguments.
+///
+/// A point (x, y) is transformed to (a * x + c * y + e, b * x + d * y + f).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        a: 1.0,
+        b: 0.0,
+        c: 0.0,
+        d: 1.0,
+        e: 0.0,
+        f: 0.0,
+    };
+
+    pub const fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+
+    pub const fn translation(x: f32, y: f32) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// `angle` is in radians, clockwise because y points down.
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    pub const fn scaling(x: f32, y: f32) -> Self {
+        Self::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// Returns a transform that applies `other` first and then `self`.
+    pub fn multiply(&self, other: &Transform) -> Self {
+        Self {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    pub fn apply(&self, x: f32, y: f32) -> [f32; 2] {
+        [
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        ]
+    }
+
+    /// The average factor lengths are scaled by.
+    pub fn average_scale(&self) -> f32 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    /// Returns `None` if the transform collapses everything onto a line or a point.
+    pub fn inverse(&self) -> Option<Self> {
+        let determinant = self.a * self.d - self.b * self.c;
+        if determinant == 0.0 {
+            return None;
+        }
+        let a = self.d / determinant;
+        let b = -self.b / determinant;
+        let c = -self.c / determinant;
+        let d = self.a / determinant;
+        Some(Self {
+            a,
+            b,
+            c,
+            d,
+            e: -(a * self.e + c * self.f),
+            f: -(b * self.e + d * self.f),
+        })
+    }
+}
+
+struct TransformStack {
+    current: Transform,
+    saved: Vec<Transform>,
+}
+
+thread_local! {
+    // The host only ever sees the composed result, so composition is exact and matches the rasterizer.
+    static TRANSFORM_STACK: RefCell<TransformStack> = const {
+        RefCell::new(TransformStack {
+            current: Transform::IDENTITY,
+            saved: Vec::new(),
+        })
+    };
+}
+
+/// Changes the current transform and records the result.
+fn update(f: impl FnOnce(&mut Transform)) {
+    let current = TRANSFORM_STACK.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        f(&mut stack.current);
+        stack.current
+    });
+    let Transform { a, b, c, d, e, f } = current;
+    record(DrawCommand::SetTransform { a, b, c, d, e, f });
+}
+
+/// Returns the transform applied to everything drawn.
+pub fn current_transform() -> Transform {
+    TRANSFORM_STACK.with(|stack| stack.borrow().current)
+}
+
+/// Replaces the current transform. Every frame starts with the identity transform.
+pub fn set_transform(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) {
+    update(|current| *current = Transform::new(a, b, c, d, e, f));
+}
+
+/// Resets the transform to the identity transform.
+pub fn reset_transform() {
+    update(|current| *current = Transform::IDENTITY);
+}
+
+/// Applies `(a, b, c, d, e, f)` before the current transform, like the canvas `transform` method.
+pub fn transform(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) {
+    update(|current| *current = current.multiply(&Transform::new(a, b, c, d, e, f)));
+}
+
+pub fn translate(x: f32, y: f32) {
+    update(|current| *current = current.multiply(&Transform::translation(x, y)));
+}
+
+/// `angle` is in radians, clockwise because y points down.
+pub fn rotate(angle: f32) {
+    update(|current| *current = current.multiply(&Transform::rotation(angle)));
+}
+
+pub fn scale(x: f32, y: f32) {
+    update(|current| *current = current.multiply(&Transform::scaling(x, y)));
+}
+
+/// Saves the current transform so a later `pop_transform` can return to it.
+pub fn push_transform() {
+    TRANSFORM_STACK.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        let current = stack.current;
+        stack.saved.push(current);
+    });
+}
+
+/// Returns to the transform saved by the matching `push_transform`.
+/// Does nothing if there is no saved transform.
+pub fn pop_transform() {
+    let saved = TRANSFORM_STACK.with(|stack| stack.borrow_mut().saved.pop());
+    if let Some(saved) = saved {
+        update(|current| *current = saved);
+    }
+}
+
+/// Matches the host, which resets its transform at the start of every draw list.
+pub(crate) fn reset_transform_stack() {
+    TRANSFORM_STACK.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        stack.current = Transform::IDENTITY;
+        stack.saved.clear();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms_compose_like_canvas() {
+        reset_transform_stack();
+        translate(10.0, 20.0);
+        push_transform();
+        scale(2.0, 2.0);
+        rotate(std::f32::consts::FRAC_PI_2);
+
+        // Rotating (1, 0) a quarter turn clockwise gives (0, 1), which is scaled and then translated.
+        let [x, y] = current_transform().apply(1.0, 0.0);
+        assert!((x - 10.0).abs() < 1e-5 && (y - 22.0).abs() < 1e-5);
+
+        pop_transform();
+        assert_eq!(current_transform(), Transform::translation(10.0, 20.0));
+        pop_transform();
+        assert_eq!(current_transform(), Transform::translation(10.0, 20.0));
+    }
+}
@@ -0,0 +1,27 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use super::{local_player, Viewport};
+
+thread_local! {
+    // Every player's viewport is shared so pointer positions can be converted the same way on every peer.
+    static VIEWPORTS: RefCell<BTreeMap<u32, Viewport>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+pub(crate) fn set_viewport(player: u32, viewport: Viewport) {
+    VIEWPORTS.with(|viewports| viewports.borrow_mut().insert(player, viewport));
+}
+
+pub(crate) fn remove_player_viewport(player: u32) {
+    VIEWPORTS.with(|viewports| viewports.borrow_mut().remove(&player));
+}
+
+/// Returns `player`'s last reported viewport, or `None` if their host has not reported one yet.
+pub fn viewport(player: u32) -> Option<Viewport> {
+    VIEWPORTS.with(|viewports| viewports.borrow().get(&player).copied())
+}
+
+/// Returns the viewport of the screen being drawn to. Only meaningful during `Game::draw`.
+pub fn local_viewport() -> Option<Viewport> {
+    local_player().and_then(viewport)
+}
@@ -0,0 +1,437 @@
+use kmath::*;
+
+use crate::mini_engine::determinism::DeterminismHarness;
+use crate::mini_engine::native::{take_state_hash_mismatches, Harness};
+use crate::mini_engine::{
+    pointer_state, recorded_state_hash, time, DrawCommand, Event, Key, PointerButton,
+    PointerDetails, PointerKind,
+};
+use crate::{is_cursor_visible, BlockGame};
+
+const PLAYER: u32 = 1;
+
+fn golden_path(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("golden")
+        .join(name)
+}
+
+/// Compares `hash` with the one stored at `path`, like `Canvas::assert_matches_golden`.
+///
+/// If the `UPDATE_GOLDEN` environment variable is set, `hash` is written to `path` instead.
+fn assert_matches_golden_hash(path: std::path::PathBuf, hash: u64) {
+    let hash = format!("{:016x}", hash);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &hash).unwrap();
+        println!("Wrote golden hash: {}", path.display());
+        return;
+    }
+    let Ok(expected) = std::fs::read_to_string(&path) else {
+        panic!(
+            "Golden hash {} is missing. The state hash was {}. Run with UPDATE_GOLDEN=1 to accept it.",
+            path.display(),
+            hash
+        );
+    };
+    assert_eq!(
+        hash,
+        expected.trim(),
+        "The state hash differs from {}",
+        path.display()
+    );
+}
+
+#[test]
+fn settled_world_draws_every_body() {
+    let mut harness = Harness::<BlockGame>::new();
+    harness.send(Event::PlayerJoined { player: PLAYER });
+    harness.fixed_updates(120);
+
+    let commands = harness.draw();
+    let shapes = commands
+        .iter()
+        .filter(|c| {
+            matches!(
+                c,
+                DrawCommand::DrawCircle { .. } | DrawCommand::DrawRect { .. } | DrawCommand::Fill
+            )
+        })
+        .count();
+    assert_eq!(shapes, harness.game.physics_objects.len());
+}
+
+#[test]
+fn settled_world_matches_golden_image() {
+    let mut harness = Harness::<BlockGame>::new();
+    harness.send(Event::PlayerJoined { player: PLAYER });
+    harness.fixed_updates(120);
+
+    // A mouse cursor stays visible after it is released.
+    harness.send(Event::PointerDown {
+        player: PLAYER,
+        pointer_id: 0,
+        x: 200.0,
+        y: 200.0,
+        details: PointerDetails::default(),
+    });
+    harness.send(Event::PointerUp {
+        player: PLAYER,
+        pointer_id: 0,
+        x: 200.0,
+        y: 200.0,
+        details: PointerDetails::default(),
+    });
+
+    harness
+        .render(1000, 700)
+        .assert_matches_golden(golden_path("block_game_settled.png"), 2);
+}
+
+#[test]
+fn grabbed_body_follows_pointer() {
+    let mut harness = Harness::<BlockGame>::new();
+    harness.send(Event::PlayerJoined { player: PLAYER });
+    harness.fixed_updates(120);
+
+    let handle = harness.game.physics_objects[1].rigid_body_handle;
+    let start = *harness.game.rapier.rigid_body_set[handle].translation();
+    let start = Vec2::new(start.x, start.y);
+    // Up and to the left of the stack, where nothing else should be in the way.
+    let target = start + Vec2::new(-0.4, -0.5);
+
+    let [x, y] = harness.game.cameras[&PLAYER].world_to_screen(start.x, start.y);
+    harness.send(Event::PointerDown {
+        player: PLAYER,
+        pointer_id: 0,
+        x,
+        y,
+        details: PointerDetails::default(),
+    });
+    let [x, y] = harness.game.cameras[&PLAYER].world_to_screen(target.x, target.y);
+    harness.send(Event::PointerMove {
+        player: PLAYER,
+        pointer_id: 0,
+        x,
+        y,
+        details: PointerDetails::default(),
+    });
+    harness.fixed_updates(10);
+
+    let end = harness.game.rapier.rigid_body_set[handle].translation();
+    assert!((end.x - target.x).abs() < 0.05);
+    assert!((end.y - target.y).abs() < 0.05);
+}
+
+#[test]
+fn released_body_is_thrown_after_a_fixed_update() {
+    let mut harness = Harness::<BlockGame>::new();
+    harness.send(Event::PlayerJoined { player: PLAYER });
+    harness.fixed_updates(120);
+
+    let handle = harness.game.physics_objects[1].rigid_body_handle;
+    let start = *harness.game.rapier.rigid_body_set[handle].translation();
+    let [x, y] = harness.game.cameras[&PLAYER].world_to_screen(start.x, start.y);
+    let details = PointerDetails::default();
+    harness.send(Event::PointerDown {
+        player: PLAYER,
+        pointer_id: 0,
+        x,
+        y,
+        details,
+    });
+    // Drag up and to the left, then let go right after a fixed update without moving again.
+    for i in 1..=5 {
+        harness.send(Event::PointerMove {
+            player: PLAYER,
+            pointer_id: 0,
+            x: x - i as f32 * 10.0,
+            y: y - i as f32 * 10.0,
+            details,
+        });
+        harness.fixed_updates(1);
+    }
+    harness.send(Event::PointerUp {
+        player: PLAYER,
+        pointer_id: 0,
+        x: x - 50.0,
+        y: y - 50.0,
+        details,
+    });
+
+    let linvel = harness.game.rapier.rigid_body_set[handle].linvel();
+    assert!(linvel.x < 0.0 && linvel.y < 0.0, "{:?}", linvel);
+}
+
+#[test]
+fn canceled_pointer_releases_body() {
+    let mut harness = Harness::<BlockGame>::new();
+    harness.send(Event::PlayerJoined { player: PLAYER });
+    harness.fixed_updates(120);
+
+    let handle = harness.game.physics_objects[1].rigid_body_handle;
+    let start = *harness.game.rapier.rigid_body_set[handle].translation();
+    let [x, y] = harness.game.cameras[&PLAYER].world_to_screen(start.x, start.y);
+    let details = PointerDetails {
+        kind: PointerKind::Touch,
+        pressure: 0.5,
+        ..Default::default()
+    };
+    harness.send(Event::PointerDown {
+        player: PLAYER,
+        pointer_id: 0,
+        x,
+        y,
+        details,
+    });
+    assert_eq!(
+        harness.game.rapier.rigid_body_set[handle].gravity_scale(),
+        0.0
+    );
+
+    harness.send(Event::PointerCancel {
+        player: PLAYER,
+        pointer_id: 0,
+        x,
+        y,
+        details,
+    });
+    let pointer = &harness.game.player_pointers[&(PLAYER, 0)];
+    assert!(pointer.moving_collider.is_none());
+    assert!(!is_cursor_visible(&pointer_state(PLAYER, 0).unwrap()));
+    assert_eq!(
+        harness.game.rapier.rigid_body_set[handle].gravity_scale(),
+        1.0
+    );
+}
+
+#[test]
+fn secondary_button_deletes_body() {
+    let mut harness = Harness::<BlockGame>::new();
+    harness.send(Event::PlayerJoined { player: PLAYER });
+    harness.fixed_updates(120);
+
+    let object_count = harness.game.physics_objects.len();
+    let handle = harness.game.physics_objects[1].rigid_body_handle;
+    let start = *harness.game.rapier.rigid_body_set[handle].translation();
+    let [x, y] = harness.game.cameras[&PLAYER].world_to_screen(start.x, start.y);
+    let details = PointerDetails {
+        button: PointerButton::Secondary,
+        ..Default::default()
+    };
+    harness.send(Event::PointerDown {
+        player: PLAYER,
+        pointer_id: 0,
+        x,
+        y,
+        details,
+    });
+    // Deleting is an action, so it happens on the next fixed update.
+    harness.fixed_updates(1);
+
+    assert_eq!(harness.game.physics_objects.len(), object_count - 1);
+    assert!(harness.game.rapier.rigid_body_set.get(handle).is_none());
+    assert!(harness.game.player_pointers[&(PLAYER, 0)]
+        .moving_collider
+        .is_none());
+}
+
+#[test]
+fn mismatched_state_hash_is_passed_to_the_host() {
+    let mut harness = Harness::<BlockGame>::new();
+    harness.send(Event::PlayerJoined { player: PLAYER });
+    harness.fixed_updates(10);
+
+    let tick = time().tick;
+    let hash = harness.state_hash();
+    assert_eq!(recorded_state_hash(tick), Some(hash));
+    harness.fixed_updates(1);
+    assert_ne!(harness.state_hash(), hash);
+
+    take_state_hash_mismatches();
+    harness.send(Event::StateHashReported {
+        player: 2,
+        tick,
+        hash,
+    });
+    assert!(take_state_hash_mismatches().is_empty());
+    harness.send(Event::StateHashReported {
+        player: 2,
+        tick: tick + 1,
+        hash,
+    });
+    assert_eq!(take_state_hash_mismatches(), [(2, tick as u32 + 1)]);
+}
+
+#[test]
+fn late_input_replays_to_the_same_state() {
+    // Only used to find where bodies start on screen.
+    let mut layout = Harness::<BlockGame>::new();
+    layout.send(Event::PlayerJoined { player: PLAYER });
+    let screen_position = |index: usize| {
+        let handle = layout.game.physics_objects[index].rigid_body_handle;
+        let position = layout.game.rapier.rigid_body_set[handle].translation();
+        layout.game.cameras[&PLAYER].world_to_screen(position.x, position.y)
+    };
+
+    let other_player = 2;
+    let mut harness = DeterminismHarness::<BlockGame>::new();
+    harness.send(0, Event::PlayerJoined { player: PLAYER });
+    harness.send(
+        0,
+        Event::PlayerJoined {
+            player: other_player,
+        },
+    );
+    // Each player drags a different body, and the other player's input always arrives late.
+    for (player, index, delay) in [(PLAYER, 1, 0), (other_player, 9, 3)] {
+        let [x, y] = screen_position(index);
+        let (pointer_id, details) = (0, PointerDetails::default());
+        harness.send_late(
+            5,
+            delay,
+            Event::PointerDown {
+                player,
+                pointer_id,
+                x,
+                y,
+                details,
+            },
+        );
+        for tick in 6..20 {
+            harness.send_late(
+                tick,
+                delay,
+                Event::PointerMove {
+                    player,
+                    pointer_id,
+                    x: x - tick as f32 * 4.0,
+                    y: y - tick as f32 * 6.0,
+                    details,
+                },
+            );
+        }
+        harness.send_late(
+            20,
+            delay,
+            Event::PointerUp {
+                player,
+                pointer_id,
+                x: x - 80.0,
+                y: y - 120.0,
+                details,
+            },
+        );
+    }
+    // Spawning draws from the simulation's random numbers.
+    harness.send_late(
+        25,
+        2,
+        Event::KeyDown {
+            player: other_player,
+            key: Key::Space,
+        },
+    );
+    harness.run(60);
+}
+
+#[test]
+fn overlapping_grabs_apply_in_the_same_order() {
+    let mut layout = Harness::<BlockGame>::new();
+    layout.send(Event::PlayerJoined { player: PLAYER });
+    let handle = layout.game.physics_objects[1].rigid_body_handle;
+    let start = *layout.game.rapier.rigid_body_set[handle].translation();
+    let [x, y] = layout.game.cameras[&PLAYER].world_to_screen(start.x, start.y);
+
+    // Several players hold the same body and pull it different ways,
+    // so the result depends on the order their grabs are applied in.
+    let mut harness = DeterminismHarness::<BlockGame>::new();
+    for player in 1..=8u32 {
+        harness.send(0, Event::PlayerJoined { player });
+        let details = PointerDetails::default();
+        harness.send_late(
+            5,
+            player as u64 % 3,
+            Event::PointerDown {
+                player,
+                pointer_id: 0,
+                x,
+                y,
+                details,
+            },
+        );
+        harness.send_late(
+            8,
+            player as u64 % 3,
+            Event::PointerMove {
+                player,
+                pointer_id: 0,
+                x: x + player as f32 * 10.0,
+                y: y - player as f32 * 5.0,
+                details,
+            },
+        );
+    }
+    harness.run(20);
+}
+
+/// Rapier and the game's math are deterministic across platforms, so this hash should match
+/// what browsers get for the same events.
+#[test]
+fn scenario_matches_golden_state_hash() {
+    // Sent in the order and with the values `index.ts` sends them in once connected.
+    let mut harness = Harness::<BlockGame>::with_seed(1234);
+    harness.send(Event::SetFixedUpdateInterval {
+        seconds: (1000.0_f64 / 60.0) as f32 / 1000.0,
+    });
+    harness.send(Event::PlayerJoined { player: PLAYER });
+    harness.fixed_updates(30);
+
+    // Throw a box at the stack, rotating it on the way, then spawn a body.
+    let handle = harness.game.physics_objects[10].rigid_body_handle;
+    let start = *harness.game.rapier.rigid_body_set[handle].translation();
+    let [x, y] = harness.game.cameras[&PLAYER].world_to_screen(start.x, start.y);
+    let details = PointerDetails::default();
+    harness.send(Event::PointerDown {
+        player: PLAYER,
+        pointer_id: 0,
+        x,
+        y,
+        details,
+    });
+    harness.send(Event::KeyDown {
+        player: PLAYER,
+        key: Key::E,
+    });
+    for i in 1..=10 {
+        harness.send(Event::PointerMove {
+            player: PLAYER,
+            pointer_id: 0,
+            x: x - i as f32 * 15.0,
+            y: y - i as f32 * 5.0,
+            details,
+        });
+        harness.fixed_updates(1);
+    }
+    harness.send(Event::KeyUp {
+        player: PLAYER,
+        key: Key::E,
+    });
+    harness.send(Event::PointerUp {
+        player: PLAYER,
+        pointer_id: 0,
+        x: x - 150.0,
+        y: y - 50.0,
+        details,
+    });
+    harness.send(Event::KeyDown {
+        player: PLAYER,
+        key: Key::Space,
+    });
+    harness.fixed_updates(240);
+
+    assert_matches_golden_hash(
+        golden_path("block_game_scenario.hash"),
+        harness.state_hash(),
+    );
+}